@@ -1,11 +1,5 @@
-use sqlx::PgPool;
-
-pub async fn user_table(pool: &PgPool) -> Result<(), sqlx::Error> {
-    sqlx::query(USER_TABLE).execute(pool).await?;
-    Ok(())
-}
-
-const USER_TABLE: &str = "
+-- Baseline schema, previously created at startup by `Tables::initialize_tables`.
+-- `IF NOT EXISTS` lets deployments that predate migrations adopt this version.
 CREATE TABLE IF NOT EXISTS users (
     id SERIAL PRIMARY KEY,
     username VARCHAR(50) UNIQUE NOT NULL,
@@ -18,5 +12,4 @@ CREATE TABLE IF NOT EXISTS users (
     created_by VARCHAR(50) NOT NULL,
     write_date TIMESTAMP,
     update_by VARCHAR(50)
-)
-";
+);
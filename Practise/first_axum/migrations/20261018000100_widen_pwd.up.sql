@@ -0,0 +1,2 @@
+-- Match play_security, whose pwd column holds bcrypt hashes and future formats.
+ALTER TABLE users ALTER COLUMN pwd TYPE VARCHAR(255);
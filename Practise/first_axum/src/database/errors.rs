@@ -0,0 +1,37 @@
+use sqlx::error::ErrorKind;
+use std::fmt;
+
+/// Storage failures the handlers need to tell apart.
+#[derive(Debug)]
+pub enum DbError {
+    /// A unique column already holds the value, named by its constraint,
+    /// e.g. `users_email_key`.
+    UniqueViolation {
+        constraint: String,
+    },
+    Other(sqlx::Error),
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::Database(db_err) if db_err.kind() == ErrorKind::UniqueViolation => {
+                DbError::UniqueViolation {
+                    constraint: db_err.constraint().unwrap_or_default().to_string(),
+                }
+            }
+            _ => DbError::Other(err),
+        }
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::UniqueViolation { constraint } => {
+                write!(f, "unique violation on {constraint}")
+            }
+            DbError::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use sqlx::PgPool;
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use tracing::error;
+
+/// Migrations under `migrations/`, embedded at compile time. Applied versions
+/// and their checksums are tracked in `_sqlx_migrations`. play_security's
+/// migrations reuse these versions in the same table, so each app needs a
+/// database of its own.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Applies pending migrations, refusing to touch a database that was
+/// migrated by a newer binary or by another application.
+pub async fn run(pool: &PgPool) -> Result<(), MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+    drop(conn);
+
+    if let Some(foreign) = applied.iter().find(|a| {
+        MIGRATOR
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .any(|m| m.version == a.version && m.checksum != a.checksum)
+    }) {
+        error!(
+            "MIGRATIONS: Migration {} was applied with a different checksum, so this database was migrated by another application; first_axum needs a database of its own",
+            foreign.version
+        );
+        return Err(MigrateError::VersionMismatch(foreign.version));
+    }
+    if let Some(unknown) = applied.iter().find(|m| !MIGRATOR.version_exists(m.version)) {
+        error!(
+            "MIGRATIONS: Database schema is at version {} which this binary does not know; refusing to start",
+            unknown.version
+        );
+        return Err(MigrateError::VersionMissing(unknown.version));
+    }
+    MIGRATOR.run(pool).await
+}
@@ -1,4 +1,6 @@
-pub mod models;
+pub mod errors;
+pub mod migrations;
+pub mod repository;
 
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use std::time::Duration;
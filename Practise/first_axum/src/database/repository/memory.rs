@@ -0,0 +1,108 @@
+use super::UserRepository;
+use crate::database::errors::DbError;
+use crate::routes::users::UserData;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Process-local storage for tests. Enforces the same unique columns as the
+/// `users` table, reporting them under the constraint names Postgres uses.
+#[derive(Default)]
+pub struct MemoryStore {
+    users: Mutex<Users>,
+}
+
+#[derive(Default)]
+struct Users {
+    rows: BTreeMap<i32, UserData>,
+    last_id: i32,
+}
+
+impl Users {
+    /// The unique constraint `user` would break, ignoring the row `id`.
+    fn conflict(&self, user: &UserData, id: Option<i32>) -> Option<DbError> {
+        let others = || self.rows.values().filter(|row| Some(row.id) != id);
+        let constraint = if others().any(|row| row.username == user.username) {
+            "users_username_key"
+        } else if others().any(|row| row.phone == user.phone) {
+            "users_phone_key"
+        } else if others().any(|row| row.email == user.email) {
+            "users_email_key"
+        } else {
+            return None;
+        };
+        Some(DbError::UniqueViolation {
+            constraint: constraint.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryStore {
+    async fn list(&self) -> Result<Vec<UserData>, DbError> {
+        Ok(self.users.lock().unwrap().rows.values().cloned().collect())
+    }
+
+    async fn find(&self, id: i32) -> Result<Option<UserData>, DbError> {
+        Ok(self.users.lock().unwrap().rows.get(&id).cloned())
+    }
+
+    async fn create(&self, user: &UserData, _pwd: &str) -> Result<u64, DbError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(conflict) = users.conflict(user, None) {
+            return Err(conflict);
+        }
+        users.last_id += 1;
+        let id = users.last_id;
+        users.rows.insert(
+            id,
+            UserData {
+                id,
+                create_date: Utc::now().naive_utc(),
+                write_date: None,
+                update_by: None,
+                ..user.clone()
+            },
+        );
+        Ok(1)
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        user: &UserData,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError> {
+        let mut users = self.users.lock().unwrap();
+        if !users.rows.contains_key(&id) {
+            return Ok(0);
+        }
+        if let Some(conflict) = users.conflict(user, Some(id)) {
+            return Err(conflict);
+        }
+        let row = users.rows.get_mut(&id).unwrap();
+        row.username = user.username.clone();
+        row.name = user.name.clone();
+        row.surname = user.surname.clone();
+        row.phone = user.phone.clone();
+        row.email = user.email.clone();
+        row.update_by = user.update_by.clone();
+        row.write_date = Some(write_date);
+        Ok(1)
+    }
+
+    async fn remove(&self, id: i32) -> Result<u64, DbError> {
+        let removed = self.users.lock().unwrap().rows.remove(&id);
+        Ok(removed.map_or(0, |_| 1))
+    }
+
+    async fn remove_many(&self, ids: &[i32]) -> Result<u64, DbError> {
+        let mut users = self.users.lock().unwrap();
+        let removed = ids
+            .iter()
+            .filter(|id| users.rows.remove(id).is_some())
+            .count();
+        Ok(removed as u64)
+    }
+}
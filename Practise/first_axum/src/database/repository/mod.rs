@@ -0,0 +1,32 @@
+pub mod memory;
+pub mod postgres;
+
+pub use memory::MemoryStore;
+pub use postgres::PgStore;
+
+use crate::database::errors::DbError;
+use crate::routes::users::UserData;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+/// User storage behind the `/users` routes. Counts returned by the write
+/// methods are rows affected, so `0` means no user matched.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn list(&self) -> Result<Vec<UserData>, DbError>;
+
+    async fn find(&self, id: i32) -> Result<Option<UserData>, DbError>;
+
+    async fn create(&self, user: &UserData, pwd: &str) -> Result<u64, DbError>;
+
+    async fn update(
+        &self,
+        id: i32,
+        user: &UserData,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError>;
+
+    async fn remove(&self, id: i32) -> Result<u64, DbError>;
+
+    async fn remove_many(&self, ids: &[i32]) -> Result<u64, DbError>;
+}
@@ -0,0 +1,139 @@
+use super::UserRepository;
+use crate::database::errors::DbError;
+use crate::routes::users::UserData;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::PgPool;
+
+pub struct PgStore {
+    pool: PgPool,
+}
+
+impl PgStore {
+    pub fn new(pool: PgPool) -> PgStore {
+        PgStore { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgStore {
+    async fn list(&self) -> Result<Vec<UserData>, DbError> {
+        Ok(sqlx::query_as::<_, UserData>(GET_ALL_USERS)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn find(&self, id: i32) -> Result<Option<UserData>, DbError> {
+        Ok(sqlx::query_as::<_, UserData>(FETCH_SINGLE_USER)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn create(&self, user: &UserData, pwd: &str) -> Result<u64, DbError> {
+        let result = sqlx::query(CREATE_USER)
+            .bind(&user.username)
+            .bind(&user.name)
+            .bind(&user.surname)
+            .bind(&user.phone)
+            .bind(&user.email)
+            .bind(pwd)
+            .bind(&user.created_by)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        user: &UserData,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError> {
+        let result = sqlx::query(UPDATE_USER)
+            .bind(&user.username)
+            .bind(&user.name)
+            .bind(&user.surname)
+            .bind(&user.phone)
+            .bind(&user.email)
+            .bind(&user.update_by)
+            .bind(write_date)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn remove(&self, id: i32) -> Result<u64, DbError> {
+        let result = sqlx::query(REMOVE_USER)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn remove_many(&self, ids: &[i32]) -> Result<u64, DbError> {
+        let result = sqlx::query(REMOVE_MULTIPLE_USERS)
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+const GET_ALL_USERS: &str = "
+SELECT id
+    ,username
+    ,name
+    ,surname
+    ,phone
+    ,email
+    ,create_date
+    ,created_by
+    ,write_date
+    ,update_by
+FROM users 
+ORDER BY id
+";
+
+const FETCH_SINGLE_USER: &str = "
+SELECT id
+    ,username
+    ,name
+    ,surname
+    ,phone
+    ,email
+    ,create_date
+    ,created_by
+    ,write_date
+    ,update_by
+FROM users 
+WHERE id = $1
+";
+
+const REMOVE_USER: &str = "
+DELETE FROM users
+WHERE id = $1
+";
+
+const REMOVE_MULTIPLE_USERS: &str = "
+DELETE FROM users
+WHERE id = ANY($1)
+";
+
+const CREATE_USER: &str = "
+INSERT INTO users (username, name, surname, phone, email, pwd, created_by)
+VALUES($1, $2, $3, $4, $5, $6, $7)
+";
+
+const UPDATE_USER: &str = "
+UPDATE users
+SET username = $1
+    ,name = $2
+    ,surname = $3
+    ,phone = $4
+    ,email = $5
+    ,update_by = $6
+    ,write_date = $7
+WHERE id = $8
+";
@@ -0,0 +1,13 @@
+pub mod database;
+pub mod response;
+pub mod routes;
+
+use axum::Router;
+use database::repository::UserRepository;
+use std::sync::Arc;
+
+pub fn app(users: Arc<dyn UserRepository>) -> Router {
+    routes::home::home_route()
+        .merge(routes::users::users_route(users))
+        .fallback(routes::error::error_route)
+}
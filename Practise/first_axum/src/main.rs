@@ -1,14 +1,20 @@
-mod database;
-mod response;
-mod routes;
-
+use first_axum::database;
+use first_axum::database::repository::PgStore;
 use sqlx::PgPool;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
-use tracing::info;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Exit status when requests were still running at the shutdown deadline.
+const EXIT_DRAIN_TIMEOUT: u8 = 2;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     dotenv::dotenv().expect("Failed to load .env file");
     tracing_subscriber::fmt()
         .with_target(false)
@@ -25,17 +31,15 @@ async fn main() {
         .expect("Failed to connect to database");
 
     info!("Database connection established");
-    //tables
-    database::models::Tables::initialize_tables(&pool)
+    //Migrations
+    database::migrations::run(&pool)
         .await
-        .expect("Failed to initialize tables");
+        .expect("Failed to apply database migrations");
 
-    info!("Tables initialized successfully");
+    info!("Migrations applied successfully");
 
     //Routes
-    let app = routes::home::home_route()
-        .merge(routes::users::users_route(pool.clone()))
-        .fallback(routes::error::error_route);
+    let app = first_axum::app(Arc::new(PgStore::new(pool.clone())));
     info!("Routes initialized successfully");
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
@@ -43,7 +47,80 @@ async fn main() {
     info!("Server listening on port {}", addr.port());
     info!("Server running on http://{}", addr);
 
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap();
+    let shutdown_timeout: u64 = std::env::var("SHUTDOWN_TIMEOUT_SECS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30);
+
+    // Set once a signal arrives, which starts the drain deadline.
+    let (signalled_tx, mut signalled) = watch::channel(None::<Instant>);
+    let server =
+        axum::serve(listener, app.into_make_service()).with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            let _ = signalled_tx.send(Some(Instant::now()));
+        });
+    let shutdown_timeout = Duration::from_secs(shutdown_timeout);
+    let deadline = async {
+        let _ = signalled.wait_for(Option::is_some).await;
+        tokio::time::sleep(shutdown_timeout).await;
+    };
+
+    let code = tokio::select! {
+        result = server.into_future() => match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("Server error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        _ = deadline => {
+            warn!(
+                "Connections still open after {}s, closing them",
+                shutdown_timeout.as_secs()
+            );
+            ExitCode::from(EXIT_DRAIN_TIMEOUT)
+        }
+    };
+
+    // A request cut off at the deadline can still hold a pooled connection,
+    // so closing the pool only gets what is left of the deadline.
+    let elapsed = signalled.borrow().map_or(Duration::ZERO, |at| at.elapsed());
+    let remaining = shutdown_timeout.saturating_sub(elapsed);
+    match tokio::time::timeout(remaining, pool.close()).await {
+        Ok(()) => {
+            info!("Database pool closed, shutdown complete");
+            code
+        }
+        Err(_) => {
+            warn!(
+                "Database connections still busy after {}s, exiting without closing them",
+                shutdown_timeout.as_secs()
+            );
+            ExitCode::from(EXIT_DRAIN_TIMEOUT)
+        }
+    }
+}
+
+/// Resolves on Ctrl-C or SIGTERM.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut s) => {
+                s.recv().await;
+            }
+            Err(e) => {
+                warn!("SIGTERM handling unavailable: {}", e);
+                std::future::pending::<()>().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+    info!("Shutdown signal received, no longer accepting connections");
 }
@@ -1,3 +1,4 @@
+use crate::database::repository::UserRepository;
 use crate::response::responses::Response;
 use axum::{
     Json, Router,
@@ -6,7 +7,8 @@ use axum::{
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::FromRow;
+use std::sync::Arc;
 use tracing::{error, info};
 
 #[derive(Serialize)]
@@ -36,7 +38,7 @@ pub struct MultipleUsersRequest {
     pub ids: Vec<i32>,
 }
 
-#[derive(Debug, FromRow, Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct UserData {
     #[serde(skip_deserializing)]
     pub id: i32,
@@ -52,7 +54,7 @@ pub struct UserData {
     pub update_by: Option<String>,
 }
 
-pub fn users_route(pool: PgPool) -> Router {
+pub fn users_route(users: Arc<dyn UserRepository>) -> Router {
     Router::new()
         .route("/users", get(get_all_users))
         .route("/users", post(create_user))
@@ -60,18 +62,17 @@ pub fn users_route(pool: PgPool) -> Router {
         .route("/users/:id", get(get_user))
         .route("/users/:id", delete(remove_user))
         .route("/users/delete_multiple", delete(remove_multiple_users))
-        .with_state(pool)
+        .with_state(users)
 }
 
-async fn get_all_users(State(pool): State<PgPool>) -> Result<Json<AllUserFetchResponse>, Response> {
-    let users: Vec<UserData> = match sqlx::query_as::<_, UserData>(GET_ALL_USERS)
-        .fetch_all(&pool)
-        .await
-    {
+async fn get_all_users(
+    State(users): State<Arc<dyn UserRepository>>,
+) -> Result<Json<AllUserFetchResponse>, Response> {
+    let users: Vec<UserData> = match users.list().await {
         Ok(u) => u,
-        Err(_) => {
+        Err(e) => {
             error!(
-                "GET_ALL_USERS failed with error: {:?}",
+                "GET_ALL_USERS failed with error: {:?}, {e}",
                 Response::InternalError
             );
             return Err(Response::InternalError);
@@ -96,16 +97,15 @@ async fn get_all_users(State(pool): State<PgPool>) -> Result<Json<AllUserFetchRe
 
 async fn get_user(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
 ) -> Result<Json<SingleUserGetResponse>, Response> {
-    let user: Option<UserData> = match sqlx::query_as::<_, UserData>(FETCH_SINGLE_USER)
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-    {
+    let user: Option<UserData> = match users.find(id).await {
         Ok(u) => u,
-        Err(_) => {
-            error!("GET_USER failed with error: {:?}", Response::InternalError);
+        Err(e) => {
+            error!(
+                "GET_USER failed with error: {:?}, {e}",
+                Response::InternalError
+            );
             return Err(Response::InternalError);
         }
     };
@@ -124,39 +124,36 @@ async fn get_user(
 
 async fn remove_user(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    let result = match sqlx::query(REMOVE_USER).bind(id).execute(&pool).await {
+    let rows_affected = match users.remove(id).await {
         Ok(u) => u,
-        Err(_) => {
+        Err(e) => {
             error!(
-                "REMOVE_USER: Record id: {id} failed with error: {:?}",
+                "REMOVE_USER: Record id: {id} failed with error: {:?}, {e}",
                 Response::InternalError
             );
             return Err(Response::InternalError);
         }
     };
 
-    if result.rows_affected() == 0 {
+    if rows_affected == 0 {
         error!(
             "REMOVE_USER: Record id: {id} failed with error: {:?}, rows affected 0",
             Response::NoUserFound
         );
         return Err(Response::NoUserFound);
     }
-    info!(
-        "REMOVE_USER: Record id: {id} successfully removed: {:?}",
-        result
-    );
+    info!("REMOVE_USER: Record id: {id} successfully removed");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: "User was successfully deleted".to_string(),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
 
 async fn remove_multiple_users(
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
     Json(payload): Json<MultipleUsersRequest>,
 ) -> Result<Json<UserCrudResponse>, Response> {
     if payload.ids.is_empty() {
@@ -167,191 +164,88 @@ async fn remove_multiple_users(
         return Err(Response::BadRequest);
     }
 
-    let result = match sqlx::query(REMOVE_MULTIPLE_USERS)
-        .bind(&payload.ids)
-        .execute(&pool)
-        .await
-    {
+    let rows_affected = match users.remove_many(&payload.ids).await {
         Ok(u) => u,
-        Err(_) => {
+        Err(e) => {
             error!(
-                "MULTI_REMOVE_USER: failed with error: {:?}",
+                "MULTI_REMOVE_USER: failed with error: {:?}, {e}",
                 Response::InternalError
             );
             return Err(Response::InternalError);
         }
     };
 
-    if result.rows_affected() == 0 {
+    if rows_affected == 0 {
         error!(
             "MULTI_REMOVE_USER: failed with error: {:?}, rows affected 0",
             Response::NoUserFound
         );
         return Err(Response::NoUserFound);
     }
-    info!(
-        "MULTI_REMOVE_USER: Users successfully removed: {:?}",
-        result
-    );
+    info!("MULTI_REMOVE_USER: {rows_affected} users successfully removed");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: "Successfully Deleted Users".to_string(),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
 
 async fn create_user(
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
     Json(payload): Json<UserData>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    let UserData {
-        username,
-        name,
-        surname,
-        phone,
-        email,
-        created_by,
-        ..
-    } = payload;
+    let pwd: String = format!("{}#01!", &payload.username);
 
-    let pwd: String = format!("{}#01!", &username);
-
-    let result = match sqlx::query(CREATE_USER)
-        .bind(&username)
-        .bind(name)
-        .bind(surname)
-        .bind(phone)
-        .bind(email)
-        .bind(pwd)
-        .bind(created_by)
-        .execute(&pool)
-        .await
-    {
+    let rows_affected = match users.create(&payload, &pwd).await {
         Ok(r) => r,
-        Err(_) => {
+        Err(e) => {
             error!(
-                "CREATE_USER: failed with error: {:?}",
+                "CREATE_USER: failed with error: {:?}, {e}",
                 Response::InternalError
             );
             return Err(Response::InternalError);
         }
     };
+    let username = payload.username;
     info!("CREATE USER: The user: {username} was successfully created");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: format!("User: {username} was successfully created"),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
 
 async fn update_user(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
     Json(payload): Json<UserData>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    let UserData {
-        username,
-        name,
-        surname,
-        phone,
-        email,
-        update_by,
-        ..
-    } = payload;
-
     let write_date = Utc::now().naive_utc();
 
-    let result = match sqlx::query(UPDATE_USER)
-        .bind(&username)
-        .bind(name)
-        .bind(surname)
-        .bind(phone)
-        .bind(email)
-        .bind(update_by)
-        .bind(write_date)
-        .bind(id)
-        .execute(&pool)
-        .await
-    {
+    let rows_affected = match users.update(id, &payload, write_date).await {
         Ok(r) => r,
-        Err(_) => {
+        Err(e) => {
             error!(
-                "UPDATE_USER: Record id: {id} failed with error:  {:?}",
+                "UPDATE_USER: Record id: {id} failed with error: {:?}, {e}",
                 Response::InternalError
             );
             return Err(Response::InternalError);
         }
     };
 
-    if result.rows_affected() == 0 {
+    if rows_affected == 0 {
         error!(
             "UPDATE_USER: Record id: {id} failed with error: {:?} rows affected 0",
-            Response::InternalError
+            Response::NoUserFound
         );
         return Err(Response::NoUserFound);
     }
 
+    let username = payload.username;
     info!("User: {username} updated successfully");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: format!("User: {username} updated successfully"),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
-
-const GET_ALL_USERS: &str = "
-SELECT id
-    ,username
-    ,name
-    ,surname
-    ,phone
-    ,email
-    ,create_date
-    ,created_by
-    ,write_date
-    ,update_by
-FROM users 
-ORDER BY id
-";
-
-const FETCH_SINGLE_USER: &str = "
-SELECT id
-    ,username
-    ,name
-    ,surname
-    ,phone
-    ,email
-    ,create_date
-    ,created_by
-    ,write_date
-    ,update_by
-FROM users 
-WHERE id = $1
-";
-
-const REMOVE_USER: &str = "
-DELETE FROM users
-WHERE id = $1
-";
-
-const REMOVE_MULTIPLE_USERS: &str = "
-DELETE FROM users
-WHERE id = ANY($1)
-";
-
-const CREATE_USER: &str = "
-INSERT INTO users (username, name, surname, phone, email, pwd, created_by)
-VALUES($1, $2, $3, $4, $5, $6, $7)
-";
-
-const UPDATE_USER: &str = "
-UPDATE users
-SET username = $1
-    ,name = $2
-    ,surname = $3
-    ,phone = $4
-    ,email = $5
-    ,update_by = $6
-    ,write_date = $7
-WHERE id = $8
-";
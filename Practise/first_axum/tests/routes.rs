@@ -0,0 +1,358 @@
+//! Drives the router in-process against `MemoryStore`, so no database is
+//! needed.
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{Method, Request, StatusCode, header};
+use first_axum::database::repository::MemoryStore;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+struct TestApp {
+    router: Router,
+}
+
+struct TestResponse {
+    status: StatusCode,
+    body: Vec<u8>,
+}
+
+impl TestResponse {
+    fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| {
+            panic!(
+                "response is not JSON ({e}): {}",
+                String::from_utf8_lossy(&self.body)
+            )
+        })
+    }
+}
+
+impl TestApp {
+    fn new() -> TestApp {
+        TestApp {
+            router: first_axum::app(Arc::new(MemoryStore::default())),
+        }
+    }
+
+    async fn request(&self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        let builder = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(json) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(json.to_string())),
+            None => builder.body(Body::empty()),
+        };
+        let response = self.router.clone().oneshot(request.unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec();
+        TestResponse { status, body }
+    }
+
+    async fn get(&self, uri: &str) -> TestResponse {
+        self.request(Method::GET, uri, None).await
+    }
+
+    /// Creates `new_user(n)`, failing the test if that fails, and returns
+    /// its id.
+    async fn create(&self, n: u32) -> i64 {
+        let response = self
+            .request(Method::POST, "/users", Some(new_user(n)))
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{:?}", response.json());
+        let users = self.get("/users").await.json();
+        users["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|u| u["username"] == format!("user{n}"))
+            .unwrap()["id"]
+            .as_i64()
+            .unwrap()
+    }
+}
+
+/// A valid `POST /users` body; `n` keeps the unique fields apart.
+fn new_user(n: u32) -> Value {
+    json!({
+        "username": format!("user{n}"),
+        "name": "Test",
+        "surname": "User",
+        "phone": format!("+2782100{n:04}"),
+        "email": format!("user{n}@example.com"),
+        "created_by": "admin",
+    })
+}
+
+#[tokio::test]
+async fn home_responds() {
+    let app = TestApp::new();
+
+    let response = app.get("/").await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, b"Hello Axum");
+}
+
+#[tokio::test]
+async fn unknown_route_uses_error_route() {
+    let app = TestApp::new();
+
+    let response = app.get("/does-not-exist").await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let body = response.json();
+    assert_eq!(body["code"], 404);
+    assert_eq!(body["message"], "Not Found");
+}
+
+#[tokio::test]
+async fn invalid_user_id_is_rejected() {
+    let app = TestApp::new();
+
+    let response = app.get("/users/abc").await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn list_without_users_is_not_found() {
+    let app = TestApp::new();
+
+    let response = app.get("/users").await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["message"], "No User data Found");
+}
+
+#[tokio::test]
+async fn created_users_are_listed() {
+    let app = TestApp::new();
+
+    let response = app.request(Method::POST, "/users", Some(new_user(1))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json();
+    assert_eq!(body["message"], "User: user1 was successfully created");
+    assert_eq!(body["rows_affected"], 1);
+    app.create(2).await;
+
+    let response = app.get("/users").await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json();
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["data"][0]["username"], "user1");
+    assert_eq!(body["data"][1]["username"], "user2");
+    assert!(body["data"][0].get("pwd").is_none());
+}
+
+#[tokio::test]
+async fn create_rejects_duplicates() {
+    let app = TestApp::new();
+    app.create(1).await;
+
+    for field in ["username", "phone", "email"] {
+        let mut user = new_user(2);
+        user[field] = new_user(1)[field].clone();
+
+        let response = app.request(Method::POST, "/users", Some(user)).await;
+
+        assert_eq!(
+            response.status,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "{field}"
+        );
+        assert_eq!(response.json()["code"], 500);
+    }
+}
+
+#[tokio::test]
+async fn create_rejects_missing_fields() {
+    let app = TestApp::new();
+    let mut user = new_user(1);
+    user.as_object_mut().unwrap().remove("email");
+
+    let response = app.request(Method::POST, "/users", Some(user)).await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn create_rejects_malformed_json() {
+    let app = TestApp::new();
+    let request = Request::post("/users")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{not json"))
+        .unwrap();
+
+    let response = app.router.clone().oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_returns_user() {
+    let app = TestApp::new();
+    let id = app.create(1).await;
+
+    let response = app.get(&format!("/users/{id}")).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let data = &response.json()["data"];
+    assert_eq!(data["id"], id);
+    assert_eq!(data["email"], "user1@example.com");
+}
+
+#[tokio::test]
+async fn get_unknown_user_is_not_found() {
+    let app = TestApp::new();
+
+    let response = app.get("/users/999").await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["message"], "No User data Found");
+}
+
+#[tokio::test]
+async fn update_changes_user() {
+    let app = TestApp::new();
+    let id = app.create(1).await;
+    let mut user = new_user(1);
+    user["name"] = json!("Renamed");
+    user["update_by"] = json!("admin");
+
+    let response = app
+        .request(Method::PUT, &format!("/users/{id}"), Some(user))
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["rows_affected"], 1);
+    let data = app.get(&format!("/users/{id}")).await.json()["data"].clone();
+    assert_eq!(data["name"], "Renamed");
+    assert_eq!(data["update_by"], "admin");
+    assert!(data["write_date"].is_string());
+}
+
+#[tokio::test]
+async fn update_unknown_user_is_not_found() {
+    let app = TestApp::new();
+
+    let response = app
+        .request(Method::PUT, "/users/999", Some(new_user(1)))
+        .await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn update_rejects_duplicates() {
+    let app = TestApp::new();
+    app.create(1).await;
+    let id = app.create(2).await;
+    let mut user = new_user(2);
+    user["email"] = new_user(1)["email"].clone();
+
+    let response = app
+        .request(Method::PUT, &format!("/users/{id}"), Some(user))
+        .await;
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    let data = app.get(&format!("/users/{id}")).await.json()["data"].clone();
+    assert_eq!(data["email"], "user2@example.com");
+}
+
+#[tokio::test]
+async fn update_rejects_missing_fields() {
+    let app = TestApp::new();
+    let id = app.create(1).await;
+    let mut user = new_user(1);
+    user.as_object_mut().unwrap().remove("username");
+
+    let response = app
+        .request(Method::PUT, &format!("/users/{id}"), Some(user))
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn delete_removes_user() {
+    let app = TestApp::new();
+    let id = app.create(1).await;
+
+    let response = app
+        .request(Method::DELETE, &format!("/users/{id}"), None)
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["rows_affected"], 1);
+    assert_eq!(
+        app.get(&format!("/users/{id}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn delete_unknown_user_is_not_found() {
+    let app = TestApp::new();
+
+    let response = app.request(Method::DELETE, "/users/999", None).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn delete_multiple_removes_listed_users() {
+    let app = TestApp::new();
+    let first = app.create(1).await;
+    let second = app.create(2).await;
+    let kept = app.create(3).await;
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/users/delete_multiple",
+            Some(json!({ "ids": [first, second, 999] })),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["rows_affected"], 2);
+    let remaining = app.get("/users").await.json();
+    assert_eq!(remaining["total"], 1);
+    assert_eq!(remaining["data"][0]["id"], kept);
+}
+
+#[tokio::test]
+async fn delete_multiple_rejects_empty_ids() {
+    let app = TestApp::new();
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/users/delete_multiple",
+            Some(json!({ "ids": [] })),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn delete_multiple_unknown_users_is_not_found() {
+    let app = TestApp::new();
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/users/delete_multiple",
+            Some(json!({ "ids": [998, 999] })),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
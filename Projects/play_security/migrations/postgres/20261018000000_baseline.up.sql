@@ -0,0 +1,23 @@
+-- Baseline schema, previously created at startup by `Tables::initialize_tables`.
+-- `IF NOT EXISTS` lets deployments that predate migrations adopt this version.
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    username VARCHAR(50) UNIQUE NOT NULL,
+    name VARCHAR(50) NOT NULL,
+    surname VARCHAR(70) NOT NULL,
+    phone VARCHAR(20) UNIQUE NOT NULL,
+    email VARCHAR(100) UNIQUE NOT NULL,
+    pwd VARCHAR(255) NOT NULL,
+    create_date TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR(50) NOT NULL,
+    write_date TIMESTAMP,
+    update_by VARCHAR(50),
+    active BOOLEAN DEFAULT TRUE
+);
+
+CREATE TABLE IF NOT EXISTS user_login (
+    username VARCHAR(50) PRIMARY KEY,
+    token VARCHAR(255),
+    created_datetime TIMESTAMP NOT NULL,
+    expire_datetime TIMESTAMP NOT NULL
+);
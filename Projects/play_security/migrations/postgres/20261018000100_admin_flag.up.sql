@@ -0,0 +1,4 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The account seeded by earlier releases on every startup.
+UPDATE users SET is_admin = TRUE WHERE username = 'admin' AND created_by = 'admin';
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS users_session_notify ON users;
+DROP TRIGGER IF EXISTS user_login_session_notify ON user_login;
+DROP FUNCTION IF EXISTS notify_session_change();
@@ -0,0 +1,16 @@
+-- Tells every instance's session cache (`auth::session_cache`) that a
+-- user's cached session may no longer be valid.
+CREATE OR REPLACE FUNCTION notify_session_change() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('session_invalidated', OLD.username);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER user_login_session_notify
+    AFTER UPDATE OR DELETE ON user_login
+    FOR EACH ROW EXECUTE FUNCTION notify_session_change();
+
+CREATE TRIGGER users_session_notify
+    AFTER UPDATE OF username, pwd, active OR DELETE ON users
+    FOR EACH ROW EXECUTE FUNCTION notify_session_change();
@@ -0,0 +1,4 @@
+DELETE FROM user_login;
+ALTER TABLE user_login ALTER COLUMN token_hash DROP NOT NULL;
+ALTER TABLE user_login ALTER COLUMN token_hash TYPE VARCHAR(255);
+ALTER TABLE user_login RENAME COLUMN token_hash TO token;
@@ -0,0 +1,7 @@
+-- Sessions are stored as a keyed hash of the token (`auth::token::session_hash`)
+-- instead of the token itself. Existing raw tokens cannot be hashed here, so
+-- everyone has to log in again.
+DELETE FROM user_login;
+ALTER TABLE user_login RENAME COLUMN token TO token_hash;
+ALTER TABLE user_login ALTER COLUMN token_hash TYPE CHAR(64);
+ALTER TABLE user_login ALTER COLUMN token_hash SET NOT NULL;
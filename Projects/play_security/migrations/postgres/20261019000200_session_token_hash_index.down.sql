@@ -0,0 +1 @@
+DROP INDEX IF EXISTS user_login_token_hash;
@@ -0,0 +1,2 @@
+-- Opaque tokens are looked up by hash alone (`SessionRepository::find_user`).
+CREATE UNIQUE INDEX user_login_token_hash ON user_login (token_hash);
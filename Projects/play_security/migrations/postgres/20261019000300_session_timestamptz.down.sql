@@ -0,0 +1,3 @@
+ALTER TABLE user_login
+    ALTER COLUMN created_datetime TYPE TIMESTAMP,
+    ALTER COLUMN expire_datetime TYPE TIMESTAMP;
@@ -0,0 +1,7 @@
+-- Session times were TIMESTAMP, so Postgres converted the UTC values the app
+-- binds using the session TimeZone and `expire_datetime > NOW()` was only
+-- right on a UTC server. The implicit cast converts back the same way, so
+-- existing rows keep the instant they were written with.
+ALTER TABLE user_login
+    ALTER COLUMN created_datetime TYPE TIMESTAMPTZ,
+    ALTER COLUMN expire_datetime TYPE TIMESTAMPTZ;
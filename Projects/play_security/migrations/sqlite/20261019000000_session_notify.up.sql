@@ -0,0 +1,5 @@
+-- Postgres adds triggers here so every instance's session cache hears about
+-- session changes. A SQLite database has a single instance, which drops its
+-- own cache entries, so there is nothing to add; the version only keeps both
+-- migration sets in step.
+SELECT 1;
@@ -0,0 +1,2 @@
+DELETE FROM user_login;
+ALTER TABLE user_login RENAME COLUMN token_hash TO token;
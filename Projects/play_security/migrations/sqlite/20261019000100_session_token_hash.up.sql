@@ -0,0 +1,5 @@
+-- Sessions are stored as a keyed hash of the token (`auth::token::session_hash`)
+-- instead of the token itself. Existing raw tokens cannot be hashed here, so
+-- everyone has to log in again.
+DELETE FROM user_login;
+ALTER TABLE user_login RENAME COLUMN token TO token_hash;
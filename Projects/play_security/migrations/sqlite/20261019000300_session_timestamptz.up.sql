@@ -0,0 +1,4 @@
+-- Postgres moves the session times to TIMESTAMPTZ here. SQLite already stores
+-- them as RFC 3339 text with their offset, so there is nothing to change; the
+-- version only keeps both migration sets in step.
+SELECT 1;
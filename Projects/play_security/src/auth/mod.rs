@@ -1 +1,5 @@
+pub mod password;
+pub mod principal;
+pub mod rate_limit;
+pub mod session_cache;
 pub mod token;
@@ -0,0 +1,40 @@
+use crate::telemetry::metrics::record_password_hash;
+use bcrypt::{BcryptError, DEFAULT_COST, hash, verify};
+use std::sync::LazyLock;
+use std::time::Instant;
+use tracing::info_span;
+
+/// Hash checked when the username is unknown, so a failed lookup spends as
+/// long in bcrypt as a wrong password does.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash("play_security-timing-equaliser", DEFAULT_COST).expect("Failed to create dummy hash")
+});
+
+pub fn hash_password(plain: &str) -> Result<String, BcryptError> {
+    let _span = info_span!("password.hash", operation = "hash").entered();
+    let started = Instant::now();
+    let hashed = hash(plain, DEFAULT_COST);
+    record_password_hash("hash", started);
+    hashed
+}
+
+pub fn verify_password(plain: &str, hashed: &str) -> Result<bool, BcryptError> {
+    let _span = info_span!("password.hash", operation = "verify").entered();
+    let started = Instant::now();
+    let valid = verify(plain, hashed);
+    record_password_hash("verify", started);
+    valid
+}
+
+pub fn verify_dummy(plain: &str) {
+    let _span = info_span!("password.hash", operation = "verify").entered();
+    let started = Instant::now();
+    let _ = verify(plain, DUMMY_HASH.as_str());
+    record_password_hash("verify", started);
+}
+
+/// Computes the dummy hash up front so the first unknown-user login is not
+/// measurably slower than the rest.
+pub fn init_dummy_hash() {
+    LazyLock::force(&DUMMY_HASH);
+}
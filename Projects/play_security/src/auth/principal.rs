@@ -0,0 +1,38 @@
+use axum::http::Method;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "users:read")]
+    UsersRead,
+    #[serde(rename = "users:write")]
+    UsersWrite,
+}
+
+impl Permission {
+    /// Safe methods only read; everything else changes users.
+    pub fn required_for(method: &Method) -> Permission {
+        match *method {
+            Method::GET | Method::HEAD => Permission::UsersRead,
+            _ => Permission::UsersWrite,
+        }
+    }
+}
+
+/// An internal service authenticated by its TLS client certificate, as
+/// configured under `[[service_principals]]`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServicePrincipal {
+    pub name: String,
+    /// Certificate subject, e.g. `CN=billing, O=Internal`, exactly as it is
+    /// logged when an unmapped certificate is presented.
+    pub subject: String,
+    pub permissions: Vec<Permission>,
+}
+
+impl ServicePrincipal {
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+/// At most this many windows are open at once, so spraying usernames cannot
+/// grow the map without bound. A new username past the cap is limited until
+/// the oldest window closes.
+const MAX_WINDOWS: usize = 100_000;
+
+struct Window {
+    started: Instant,
+    attempts: u32,
+}
+
+#[derive(Default)]
+struct Windows {
+    by_user: HashMap<String, Window>,
+    /// Open windows in the order they started, for pruning. Entries whose
+    /// window was reset or restarted since are skipped when they expire.
+    started: VecDeque<(Instant, String)>,
+}
+
+impl Windows {
+    fn prune(&mut self, now: Instant) {
+        while let Some((started, _)) = self.started.front() {
+            if now.duration_since(*started) < WINDOW {
+                break;
+            }
+            let (started, username) = self.started.pop_front().expect("front exists");
+            if self
+                .by_user
+                .get(&username)
+                .is_some_and(|w| w.started == started)
+            {
+                self.by_user.remove(&username);
+            }
+        }
+    }
+}
+
+/// Fixed one-minute window of `/login` attempts per username. Each attempt
+/// takes a slot before the password is checked, so concurrent guesses cannot
+/// overshoot the limit, and a successful login gives the slots back, so a
+/// user who knows their password is not slowed down by their own logins.
+/// The limit is passed on each call so it follows config reloads.
+#[derive(Default)]
+pub struct LoginRateLimiter {
+    windows: Mutex<Windows>,
+}
+
+impl LoginRateLimiter {
+    /// Counts an attempt for `username` unless it is over the limit. `Err`
+    /// carries how long the caller should wait before retrying.
+    pub fn attempt(&self, username: &str, limit_per_minute: u32) -> Result<(), Duration> {
+        if limit_per_minute == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        windows.prune(now);
+
+        let username = username.to_lowercase();
+        if let Some(window) = windows.by_user.get_mut(&username) {
+            if window.attempts >= limit_per_minute {
+                return Err(WINDOW - now.duration_since(window.started));
+            }
+            window.attempts += 1;
+            return Ok(());
+        }
+
+        if windows.started.len() >= MAX_WINDOWS {
+            let oldest = windows.started.front().map_or(now, |(started, _)| *started);
+            return Err(WINDOW - now.duration_since(oldest));
+        }
+        windows.started.push_back((now, username.clone()));
+        windows.by_user.insert(
+            username,
+            Window {
+                started: now,
+                attempts: 1,
+            },
+        );
+        Ok(())
+    }
+
+    /// A successful login clears the user's attempts.
+    pub fn reset(&self, username: &str) {
+        self.windows
+            .lock()
+            .unwrap()
+            .by_user
+            .remove(&username.to_lowercase());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::config::SessionCacheConfig;
+use crate::database::errors::DbError;
+use crate::database::repository::{ActiveSession, SessionRepository};
+use crate::shutdown::Shutdown;
+use crate::telemetry::metrics::record_session_cache;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use moka::sync::Cache;
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Postgres channel the `notify_session_change` trigger publishes a
+/// username on whenever that user's session, password or active flag
+/// changes.
+pub const INVALIDATION_CHANNEL: &str = "session_invalidated";
+
+const LISTEN_RETRY: Duration = Duration::from_secs(5);
+
+/// Remembers sessions the wrapped repository found active, so repeat
+/// requests with the same token skip the lookup until the entry expires, the
+/// session itself expires or it is invalidated.
+pub struct SessionCache {
+    inner: Arc<dyn SessionRepository>,
+    /// Token hash of a cached active session to the session.
+    active: Cache<String, ActiveSession>,
+    /// Bumped on every invalidation. A lookup only fills the cache if no
+    /// invalidation ran while it was reading the repository, otherwise a
+    /// session revoked in between would be cached as active. Held while
+    /// filling and invalidating so the check and the insert are atomic.
+    generation: Mutex<u64>,
+}
+
+impl SessionCache {
+    pub fn new(inner: Arc<dyn SessionRepository>, config: &SessionCacheConfig) -> SessionCache {
+        let active = Cache::builder()
+            .max_capacity(config.max_entries)
+            .time_to_live(Duration::from_secs(config.ttl_secs))
+            .support_invalidation_closures()
+            .build();
+        SessionCache {
+            inner,
+            active,
+            generation: Mutex::new(0),
+        }
+    }
+
+    /// Takes effect for lookups immediately; the entries themselves are
+    /// dropped in the background.
+    pub fn invalidate(&self, username: &str) {
+        let mut generation = self.generation.lock().unwrap();
+        *generation += 1;
+        let username = username.to_string();
+        if let Err(e) = self
+            .active
+            .invalidate_entries_if(move |_, session| session.username == username)
+        {
+            warn!("SESSION_CACHE: Failed to invalidate, clearing cache: {}", e);
+            self.active.invalidate_all();
+        }
+    }
+
+    pub fn invalidate_all(&self) {
+        let mut generation = self.generation.lock().unwrap();
+        *generation += 1;
+        self.active.invalidate_all();
+    }
+
+    /// A cached session that has not expired yet.
+    fn cached(&self, token_hash: &str) -> Option<ActiveSession> {
+        let session = self.active.get(token_hash)?;
+        if session.expires_at > Utc::now() {
+            record_session_cache("hit");
+            return Some(session);
+        }
+        self.active.invalidate(token_hash);
+        None
+    }
+
+    /// Looks the session up in the wrapped repository and caches it unless
+    /// an invalidation ran meanwhile.
+    async fn fill<F>(&self, token_hash: &str, lookup: F) -> Result<Option<ActiveSession>, DbError>
+    where
+        F: Future<Output = Result<Option<ActiveSession>, DbError>>,
+    {
+        record_session_cache("miss");
+        let started = *self.generation.lock().unwrap();
+        let found = lookup.await?;
+        if let Some(session) = &found {
+            let generation = self.generation.lock().unwrap();
+            if *generation == started {
+                self.active.insert(token_hash.to_string(), session.clone());
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait]
+impl SessionRepository for SessionCache {
+    async fn save(
+        &self,
+        username: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DbError> {
+        let saved = self
+            .inner
+            .save(username, token_hash, created_at, expires_at)
+            .await;
+        self.invalidate(username);
+        saved
+    }
+
+    async fn find_active(
+        &self,
+        username: &str,
+        token_hash: &str,
+    ) -> Result<Option<ActiveSession>, DbError> {
+        if let Some(session) = self.cached(token_hash) {
+            return Ok((session.username == username).then_some(session));
+        }
+        self.fill(token_hash, self.inner.find_active(username, token_hash))
+            .await
+    }
+
+    async fn find_user(&self, token_hash: &str) -> Result<Option<ActiveSession>, DbError> {
+        if let Some(session) = self.cached(token_hash) {
+            return Ok(Some(session));
+        }
+        self.fill(token_hash, self.inner.find_user(token_hash))
+            .await
+    }
+
+    async fn revoke(&self, usernames: &[String]) -> Result<(), DbError> {
+        let revoked = self.inner.revoke(usernames).await;
+        for username in usernames {
+            self.invalidate(username);
+        }
+        revoked
+    }
+}
+
+/// Keeps `cache` in step with other instances sharing the database. After
+/// the notification connection drops the whole cache is cleared, since
+/// anything sent in between was missed.
+pub fn spawn_invalidation_listener(pool: PgPool, cache: Arc<SessionCache>, shutdown: &Shutdown) {
+    shutdown.spawn(async move {
+        loop {
+            let mut listener = match PgListener::connect_with(&pool).await {
+                Ok(l) => l,
+                Err(e) => {
+                    warn!("SESSION_CACHE: Failed to connect listener: {}", e);
+                    tokio::time::sleep(LISTEN_RETRY).await;
+                    continue;
+                }
+            };
+            if let Err(e) = listener.listen(INVALIDATION_CHANNEL).await {
+                warn!(
+                    "SESSION_CACHE: Failed to listen on {}: {}",
+                    INVALIDATION_CHANNEL, e
+                );
+                tokio::time::sleep(LISTEN_RETRY).await;
+                continue;
+            }
+            info!(
+                "SESSION_CACHE: Listening for invalidations on {}",
+                INVALIDATION_CHANNEL
+            );
+            cache.invalidate_all();
+
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(notification)) => cache.invalidate(notification.payload()),
+                    Ok(None) => {
+                        warn!("SESSION_CACHE: Listener reconnected, clearing cache");
+                        cache.invalidate_all();
+                    }
+                    Err(e) => {
+                        warn!("SESSION_CACHE: Listener failed: {}", e);
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(LISTEN_RETRY).await;
+        }
+    });
+}
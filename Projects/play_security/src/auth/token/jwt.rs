@@ -0,0 +1,54 @@
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use tracing::error;
+
+use super::{Token, TokenIssuer};
+use crate::config::AuthConfig;
+use crate::response::responses::Response;
+
+/// JWTs signed with `auth.jwt_secret` using the first of `auth.algorithms`.
+pub struct Jwt;
+
+impl TokenIssuer for Jwt {
+    fn issue(&self, username: &str, auth: &AuthConfig) -> Result<String, Response> {
+        encode(
+            &Header::new(auth.algorithms[0]),
+            &Token::new(username, auth),
+            &EncodingKey::from_secret(auth.jwt_secret.expose().as_bytes()),
+        )
+        .map_err(|e| {
+            error!("JWT Creation error: {:?}", e);
+            Response::InternalError
+        })
+    }
+
+    /// Tries each secret in turn so tokens signed before a key rotation stay
+    /// valid. Only a signature mismatch moves on to the next secret.
+    fn verify(&self, token: &str, auth: &AuthConfig) -> Result<Option<Token>, Response> {
+        let validation = validation(auth);
+        let mut last_err = Response::InvalidToken("No verification secret configured".to_string());
+        for secret in auth.verification_secrets() {
+            match decode::<Token>(
+                token,
+                &DecodingKey::from_secret(secret.expose().as_bytes()),
+                &validation,
+            ) {
+                Ok(token_data) => return Ok(Some(token_data.claims)),
+                Err(e) if matches!(e.kind(), ErrorKind::InvalidSignature) => last_err = e.into(),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(last_err)
+    }
+}
+
+fn validation(auth: &AuthConfig) -> Validation {
+    let mut validation = Validation::new(auth.algorithms[0]);
+    validation.algorithms = auth.algorithms.clone();
+    validation.leeway = auth.leeway_secs;
+    validation.validate_nbf = true;
+    validation.set_issuer(&[&auth.issuer]);
+    validation.set_audience(&[&auth.audience]);
+    validation.set_required_spec_claims(&["exp", "nbf", "sub", "iss", "aud"]);
+    validation
+}
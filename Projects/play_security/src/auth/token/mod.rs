@@ -0,0 +1,127 @@
+pub mod jwt;
+pub mod opaque;
+pub mod paseto;
+
+pub use jwt::Jwt;
+pub use opaque::Opaque;
+pub use paseto::{PasetoLocal, PasetoPublic};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::config::{AuthConfig, TokenFormat};
+use crate::response::responses::Response;
+
+/// Subject of the throwaway token `probe` issues; never stored.
+const PROBE_SUBJECT: &str = "readiness-probe";
+
+/// Claims carried by every token, whatever its format.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Token {
+    /// Username the token was issued to.
+    pub sub: String,
+    pub iss: String,
+    pub aud: String,
+    pub exp: usize,
+    pub nbf: usize,
+    pub iat: usize,
+    /// Unique per token, so two logins in the same second still differ.
+    pub jti: String,
+}
+
+impl Token {
+    pub fn new(username: &str, auth: &AuthConfig) -> Token {
+        let now = Utc::now();
+        let expires_at = now + auth.token_ttl();
+        let mut jti = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut jti);
+        Token {
+            sub: username.to_owned(),
+            iss: auth.issuer.clone(),
+            aud: auth.audience.clone(),
+            iat: now.timestamp() as usize,
+            nbf: now.timestamp() as usize,
+            exp: expires_at.timestamp() as usize,
+            jti: hex::encode(jti),
+        }
+    }
+
+    /// Issuer, audience and validity window, allowing `auth.leeway_secs` of
+    /// clock skew. Failures use the same wording as `jsonwebtoken`.
+    pub fn check(&self, auth: &AuthConfig) -> Result<(), Response> {
+        let now = Utc::now().timestamp() as usize;
+        let leeway = auth.leeway_secs as usize;
+        if self.iss != auth.issuer {
+            return Err(Response::InvalidToken("InvalidIssuer".to_string()));
+        }
+        if self.aud != auth.audience {
+            return Err(Response::InvalidToken("InvalidAudience".to_string()));
+        }
+        if self.exp + leeway < now {
+            return Err(Response::InvalidToken("ExpiredSignature".to_string()));
+        }
+        if self.nbf > now + leeway {
+            return Err(Response::InvalidToken("ImmatureSignature".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Creates and checks bearer tokens in one format, so `login` and
+/// `auth_middleware` work the same whichever `auth.token_format` is active.
+pub trait TokenIssuer: Send + Sync {
+    fn issue(&self, username: &str, auth: &AuthConfig) -> Result<String, Response>;
+
+    /// Checks the token's integrity and claims and returns them, or `None`
+    /// for formats without claims, whose session lookup alone identifies
+    /// the user.
+    fn verify(&self, token: &str, auth: &AuthConfig) -> Result<Option<Token>, Response>;
+}
+
+/// HMAC-SHA256 of `token` under `key`, hex encoded. Sessions are stored and
+/// looked up by this value, so reading `user_login` does not give anyone a
+/// usable token.
+pub fn session_hash(token: &str, key: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(token.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Issues a throwaway token with the active format, verifies it and hashes
+/// it with `auth.session_hash_key`, so a key that cannot sign, verify or
+/// hash shows up in readiness instead of on the next login.
+pub fn probe(auth: &AuthConfig) -> Result<(), String> {
+    let signing_key = match auth.token_format {
+        TokenFormat::Jwt => Some(("JWT secret", &auth.jwt_secret)),
+        TokenFormat::PasetoV4Local | TokenFormat::PasetoV4Public => {
+            Some(("PASETO key", &auth.paseto_key))
+        }
+        TokenFormat::Opaque => None,
+    };
+    if let Some((name, key)) = signing_key
+        && key.is_empty()
+    {
+        return Err(format!("{name} is not set"));
+    }
+    if auth.session_hash_key.is_empty() {
+        return Err("session hash key is not set".to_string());
+    }
+
+    let issuer = auth.token_issuer();
+    let token = issuer
+        .issue(PROBE_SUBJECT, auth)
+        .map_err(|e| format!("probe token could not be issued: {e:?}"))?;
+    match issuer.verify(&token, auth) {
+        Ok(Some(claims)) if claims.sub != PROBE_SUBJECT => {
+            return Err(format!("probe token verified as '{}'", claims.sub));
+        }
+        Ok(_) => {}
+        Err(e) => return Err(format!("probe token did not verify: {e:?}")),
+    }
+    session_hash(&token, auth.session_hash_key.expose());
+    Ok(())
+}
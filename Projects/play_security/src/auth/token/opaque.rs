@@ -0,0 +1,31 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+
+use super::{Token, TokenIssuer};
+use crate::config::AuthConfig;
+use crate::response::responses::Response;
+
+const TOKEN_BYTES: usize = 32;
+
+/// Random tokens with no content. Only their hash is stored, so validation is
+/// the session lookup alone: nothing to sign, no key to leak, and revocation
+/// applies on the next request.
+pub struct Opaque;
+
+impl TokenIssuer for Opaque {
+    fn issue(&self, _username: &str, _auth: &AuthConfig) -> Result<String, Response> {
+        let mut token = [0u8; TOKEN_BYTES];
+        rand::thread_rng().fill_bytes(&mut token);
+        Ok(URL_SAFE_NO_PAD.encode(token))
+    }
+
+    /// Only rejects values that could never have been issued, sparing the
+    /// lookup.
+    fn verify(&self, token: &str, _auth: &AuthConfig) -> Result<Option<Token>, Response> {
+        match URL_SAFE_NO_PAD.decode(token) {
+            Ok(bytes) if bytes.len() == TOKEN_BYTES => Ok(None),
+            _ => Err(Response::InvalidToken("InvalidToken".to_string())),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! PASETO v4 tokens (<https://github.com/paseto-standard/paseto-spec>),
+//! built with `pasetors`. The version is fixed by the header, so there is no
+//! algorithm to confuse. Issued tokens carry no footer or implicit assertion
+//! and tokens carrying a footer are rejected.
+
+use chrono::{DateTime, Utc};
+use ed25519_compact::{KeyPair, Seed};
+use pasetors::errors::Error;
+use pasetors::keys::{AsymmetricPublicKey, AsymmetricSecretKey, SymmetricKey};
+use pasetors::token::{TrustedToken, UntrustedToken};
+use pasetors::version4::{LocalToken, PublicToken, V4};
+use pasetors::{Local, Public};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::{Token, TokenIssuer};
+use crate::config::{AuthConfig, Secret};
+use crate::response::responses::Response;
+
+/// `v4.local`: claims encrypted and authenticated with the 32-byte
+/// `auth.paseto_key`, so clients cannot read them.
+pub struct PasetoLocal;
+
+/// `v4.public`: claims signed with the Ed25519 key whose 32-byte seed is
+/// `auth.paseto_key`, readable by anyone holding the token.
+pub struct PasetoPublic;
+
+impl TokenIssuer for PasetoLocal {
+    fn issue(&self, username: &str, auth: &AuthConfig) -> Result<String, Response> {
+        let key = key_bytes(&auth.paseto_key)?;
+        let payload = payload(username, auth)?;
+        SymmetricKey::<V4>::from(&key)
+            .and_then(|key| LocalToken::encrypt(&key, &payload, None, None))
+            .map_err(issue_failed)
+    }
+
+    fn verify(&self, token: &str, auth: &AuthConfig) -> Result<Option<Token>, Response> {
+        let token = untrusted::<Local>(token)?;
+        verify_with(auth, |key| {
+            LocalToken::decrypt(&SymmetricKey::<V4>::from(key)?, &token, None, None)
+        })
+        .map(Some)
+    }
+}
+
+impl TokenIssuer for PasetoPublic {
+    fn issue(&self, username: &str, auth: &AuthConfig) -> Result<String, Response> {
+        let key = key_bytes(&auth.paseto_key)?;
+        let payload = payload(username, auth)?;
+        key_pair(&key)
+            .and_then(|(secret, _)| PublicToken::sign(&secret, &payload, None, None))
+            .map_err(issue_failed)
+    }
+
+    fn verify(&self, token: &str, auth: &AuthConfig) -> Result<Option<Token>, Response> {
+        let token = untrusted::<Public>(token)?;
+        verify_with(auth, |seed| {
+            PublicToken::verify(&key_pair(seed)?.1, &token, None, None)
+        })
+        .map(Some)
+    }
+}
+
+/// Registered claims as PASETO defines them, with ISO 8601 times.
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    iss: String,
+    aud: String,
+    exp: DateTime<Utc>,
+    nbf: DateTime<Utc>,
+    iat: DateTime<Utc>,
+    jti: String,
+}
+
+impl From<Token> for Claims {
+    fn from(token: Token) -> Claims {
+        let time = |secs: usize| DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+        Claims {
+            sub: token.sub,
+            iss: token.iss,
+            aud: token.aud,
+            exp: time(token.exp),
+            nbf: time(token.nbf),
+            iat: time(token.iat),
+            jti: token.jti,
+        }
+    }
+}
+
+impl From<Claims> for Token {
+    fn from(claims: Claims) -> Token {
+        let secs = |time: DateTime<Utc>| time.timestamp().max(0) as usize;
+        Token {
+            sub: claims.sub,
+            iss: claims.iss,
+            aud: claims.aud,
+            exp: secs(claims.exp),
+            nbf: secs(claims.nbf),
+            iat: secs(claims.iat),
+            jti: claims.jti,
+        }
+    }
+}
+
+fn payload(username: &str, auth: &AuthConfig) -> Result<Vec<u8>, Response> {
+    serde_json::to_vec(&Claims::from(Token::new(username, auth))).map_err(|e| {
+        error!("PASETO: Failed to serialize claims: {:?}", e);
+        Response::InternalError
+    })
+}
+
+/// Tries the current key, then the previous ones, so tokens issued before a
+/// key rotation stay valid.
+fn verify_with(
+    auth: &AuthConfig,
+    open: impl Fn(&[u8; 32]) -> Result<TrustedToken, Error>,
+) -> Result<Token, Response> {
+    let mut last_err = invalid("No verification key configured");
+    for secret in auth.paseto_keys() {
+        match open(&key_bytes(secret)?) {
+            Ok(trusted) => {
+                let claims: Claims = serde_json::from_str(trusted.payload())
+                    .map_err(|_| invalid("InvalidClaims"))?;
+                let token = Token::from(claims);
+                token.check(auth)?;
+                return Ok(token);
+            }
+            Err(Error::TokenValidation) => last_err = invalid("InvalidSignature"),
+            Err(_) => return Err(invalid("InvalidToken")),
+        }
+    }
+    Err(last_err)
+}
+
+/// Parses `token` as a footerless v4 token of purpose `T`; issued tokens
+/// never carry a footer, so one that does was not ours.
+fn untrusted<'a, T>(token: &'a str) -> Result<UntrustedToken<T, V4>, Response>
+where
+    UntrustedToken<T, V4>: TryFrom<&'a str, Error = Error>,
+{
+    if token.split('.').count() != 3 {
+        return Err(invalid("InvalidToken"));
+    }
+    UntrustedToken::try_from(token).map_err(|_| invalid("InvalidToken"))
+}
+
+/// The Ed25519 key pair whose seed is `seed`, as pasetors expects it.
+fn key_pair(seed: &[u8; 32]) -> Result<(AsymmetricSecretKey<V4>, AsymmetricPublicKey<V4>), Error> {
+    let pair = KeyPair::from_seed(Seed::new(*seed));
+    let secret = AsymmetricSecretKey::<V4>::from(pair.sk.as_ref())?;
+    let public = AsymmetricPublicKey::<V4>::try_from(&secret)?;
+    Ok((secret, public))
+}
+
+/// Config validation guarantees 64 hex characters.
+fn key_bytes(secret: &Secret) -> Result<[u8; 32], Response> {
+    hex::decode(secret.expose())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            error!("PASETO: auth.paseto_key is not 32 bytes of hex");
+            Response::InternalError
+        })
+}
+
+fn issue_failed(e: Error) -> Response {
+    error!("PASETO: Failed to issue token: {:?}", e);
+    Response::InternalError
+}
+
+fn invalid(detail: &str) -> Response {
+    Response::InvalidToken(detail.to_string())
+}
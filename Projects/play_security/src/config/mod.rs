@@ -0,0 +1,881 @@
+pub mod reload;
+pub mod secret;
+
+pub use reload::SharedConfig;
+pub use secret::Secret;
+
+use crate::auth::principal::ServicePrincipal;
+use crate::auth::token::{Jwt, Opaque, PasetoLocal, PasetoPublic, TokenIssuer};
+use crate::database::bootstrap::AdminAccount;
+use crate::database::{MEMORY_URL, SQLITE_SCHEME};
+use crate::telemetry::redact::{self, PiiPolicy};
+use axum::http::HeaderValue;
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable naming the TOML file; `config.toml` is used when it
+/// exists and the variable is unset.
+pub const CONFIG_PATH_ENV: &str = "PLAY_SECURITY_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    #[default]
+    Development,
+    Production,
+}
+
+impl FromStr for Environment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "development" => Ok(Environment::Development),
+            "production" => Ok(Environment::Production),
+            other => Err(format!(
+                "expected 'development' or 'production', got '{other}'"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_addr: SocketAddr,
+    /// How long in-flight requests may keep running after SIGTERM or
+    /// Ctrl-C before their connections are closed.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: Secret,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: Secret::default(),
+            max_connections: 5,
+            min_connections: 1,
+            connect_timeout_secs: 30,
+            idle_timeout_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub token_format: TokenFormat,
+    /// Signs new tokens.
+    pub jwt_secret: Secret,
+    /// Still accepted when verifying, so a new signing key can be rolled out
+    /// without logging everyone out.
+    pub previous_jwt_secrets: Vec<Secret>,
+    /// 32 bytes as hex: the symmetric key for `paseto_v4_local`, the Ed25519
+    /// seed for `paseto_v4_public`.
+    pub paseto_key: Secret,
+    /// Still accepted when verifying PASETO tokens, like
+    /// `previous_jwt_secrets`.
+    pub previous_paseto_keys: Vec<Secret>,
+    /// Keys the hash of each session token kept in the database. Changing it
+    /// ends every session.
+    pub session_hash_key: Secret,
+    pub token_ttl_secs: u64,
+    /// `iss` of new tokens; tokens from any other issuer are rejected.
+    pub issuer: String,
+    /// `aud` of new tokens; tokens for any other audience are rejected.
+    pub audience: String,
+    /// Clock skew tolerated when checking `exp` and `nbf`.
+    pub leeway_secs: u64,
+    /// Accepted signing algorithms. New tokens are signed with the first.
+    pub algorithms: Vec<Algorithm>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            token_format: TokenFormat::default(),
+            jwt_secret: Secret::default(),
+            previous_jwt_secrets: Vec::new(),
+            paseto_key: Secret::default(),
+            previous_paseto_keys: Vec::new(),
+            session_hash_key: Secret::default(),
+            token_ttl_secs: 8 * 60 * 60,
+            issuer: "play_security".to_string(),
+            audience: "play_security".to_string(),
+            leeway_secs: 30,
+            algorithms: vec![Algorithm::HS256],
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn verification_secrets(&self) -> impl Iterator<Item = &Secret> {
+        std::iter::once(&self.jwt_secret).chain(self.previous_jwt_secrets.iter())
+    }
+
+    pub fn paseto_keys(&self) -> impl Iterator<Item = &Secret> {
+        std::iter::once(&self.paseto_key).chain(self.previous_paseto_keys.iter())
+    }
+
+    pub fn token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.token_ttl_secs as i64)
+    }
+
+    pub fn token_issuer(&self) -> &'static dyn TokenIssuer {
+        match self.token_format {
+            TokenFormat::Jwt => &Jwt,
+            TokenFormat::PasetoV4Local => &PasetoLocal,
+            TokenFormat::PasetoV4Public => &PasetoPublic,
+            TokenFormat::Opaque => &Opaque,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenFormat {
+    #[default]
+    Jwt,
+    PasetoV4Local,
+    PasetoV4Public,
+    /// Random tokens checked only against the session table.
+    Opaque,
+}
+
+impl FromStr for TokenFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jwt" => Ok(TokenFormat::Jwt),
+            "paseto_v4_local" => Ok(TokenFormat::PasetoV4Local),
+            "paseto_v4_public" => Ok(TokenFormat::PasetoV4Public),
+            "opaque" => Ok(TokenFormat::Opaque),
+            other => Err(format!(
+                "expected 'jwt', 'paseto_v4_local', 'paseto_v4_public' or 'opaque', got '{other}'"
+            )),
+        }
+    }
+}
+
+/// First admin account, only used while no admin exists.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    pub username: String,
+    pub name: String,
+    pub surname: String,
+    pub phone: String,
+    pub email: String,
+    pub password: Option<Secret>,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        AdminConfig {
+            username: "admin".to_string(),
+            name: "Admin".to_string(),
+            surname: "Admin".to_string(),
+            phone: String::new(),
+            email: String::new(),
+            password: None,
+        }
+    }
+}
+
+impl fmt::Debug for AdminConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminConfig")
+            .field("username", &self.username)
+            .field("name", &redact::name(&self.name))
+            .field("surname", &redact::name(&self.surname))
+            .field("phone", &redact::phone(&self.phone))
+            .field("email", &redact::email(&self.email))
+            .field("password", &self.password)
+            .finish()
+    }
+}
+
+impl AdminConfig {
+    /// `None` when no password is configured and setup should go through
+    /// the one-time token instead.
+    pub fn account(&self) -> Option<AdminAccount> {
+        let password = self.password.as_ref()?;
+        Some(AdminAccount {
+            username: self.username.clone(),
+            name: self.name.clone(),
+            surname: self.surname.clone(),
+            phone: self.phone.clone(),
+            email: self.email.clone(),
+            password: password.expose().to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Compact human readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, with the request span's fields.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("expected 'text' or 'json', got '{other}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `tracing_subscriber::EnvFilter` directive, e.g. `info` or `play_security=debug`.
+    pub level: String,
+    pub format: LogFormat,
+    /// Defaults to `partial` in debug builds and `redact` in release builds.
+    pub pii: PiiPolicy,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: "info".to_string(),
+            format: LogFormat::default(),
+            pii: PiiPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Exact origins, e.g. `https://app.example.com`. Empty disables CORS.
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Failed `/login` attempts allowed per username per minute. Anyone can
+    /// fail logins for any username, so a limit also lets them lock that
+    /// user out for the rest of the minute. `0` (the default) disables it.
+    pub login_attempts_per_minute: u32,
+}
+
+/// Validated sessions kept in memory so `auth_middleware` can skip the
+/// session lookup. Entries are dropped on logout, password change, removal
+/// and, with Postgres, when another instance changes the session.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionCacheConfig {
+    /// How long a validated session is trusted without a lookup, at most
+    /// 300. `0` disables the cache.
+    pub ttl_secs: u64,
+    pub max_entries: u64,
+}
+
+impl Default for SessionCacheConfig {
+    fn default() -> Self {
+        SessionCacheConfig {
+            ttl_secs: 30,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Built-in HTTPS. The certificate and key are re-read when either file
+/// changes or on SIGHUP, so renewals do not need a restart.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM certificate chain, leaf first.
+    pub cert_path: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1).
+    pub key_path: PathBuf,
+    /// Plain HTTP listener that redirects every request to HTTPS.
+    pub redirect_http_addr: Option<SocketAddr>,
+    /// PEM CA bundles trusted to sign client certificates. Empty disables
+    /// client certificate authentication.
+    pub client_ca_paths: Vec<PathBuf>,
+    /// Reject handshakes without a client certificate instead of falling
+    /// back to bearer tokens.
+    pub require_client_cert: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Serve Prometheus metrics at `GET /metrics`. Off by default; without
+    /// `admin_addr` they are served unauthenticated on the API port.
+    pub enabled: bool,
+    /// Serve `/metrics` on this plain HTTP address only, instead of on
+    /// `server.bind_addr`, so it can stay off the public network.
+    pub admin_addr: Option<SocketAddr>,
+}
+
+/// OpenTelemetry trace export. At most one exporter may be set; with
+/// neither, spans only feed the local logs.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TracingConfig {
+    /// OTLP/HTTP collector base URL, e.g. `http://localhost:4318`. Spans
+    /// are posted to `<endpoint>/v1/traces`.
+    pub otlp_endpoint: Option<String>,
+    /// Append spans to this file as JSON lines instead, e.g. in tests.
+    pub file_path: Option<PathBuf>,
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        TracingConfig {
+            otlp_endpoint: None,
+            file_path: None,
+            service_name: env!("CARGO_PKG_NAME").to_string(),
+        }
+    }
+}
+
+/// All runtime settings. Sources are applied in increasing precedence:
+/// built-in defaults, the TOML file, `.env`, then the process environment
+/// (`.env` never overrides a variable that is already set).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub environment: Environment,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub admin: AdminConfig,
+    pub log: LogConfig,
+    pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub session_cache: SessionCacheConfig,
+    pub tls: TlsConfig,
+    pub service_principals: Vec<ServicePrincipal>,
+    pub metrics: MetricsConfig,
+    pub tracing: TracingConfig,
+}
+
+/// Every problem found while loading, reported together so one restart is
+/// enough to fix them all.
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Settings that differ in `candidate` but are only read at startup, so
+    /// a reload cannot apply them.
+    pub fn startup_only_changes(&self, candidate: &Config) -> Vec<String> {
+        let mut changed = Vec::new();
+        if self.environment != candidate.environment {
+            changed.push("environment");
+        }
+        if self.server != candidate.server {
+            changed.push("server");
+        }
+        if self.database != candidate.database {
+            changed.push("database");
+        }
+        if self.admin != candidate.admin {
+            changed.push("admin");
+        }
+        if self.session_cache != candidate.session_cache {
+            changed.push("session_cache");
+        }
+        if self.tls != candidate.tls {
+            changed.push("tls");
+        }
+        if self.metrics != candidate.metrics {
+            changed.push("metrics");
+        }
+        if self.tracing != candidate.tracing {
+            changed.push("tracing");
+        }
+        if self.log.format != candidate.log.format {
+            changed.push("log.format");
+        }
+        changed.into_iter().map(str::to_string).collect()
+    }
+
+    /// Reads `.env` into the process environment, then loads the config.
+    /// Only called once at startup.
+    pub fn load() -> Result<Config, ConfigError> {
+        match dotenv::dotenv() {
+            Ok(_) => Config::from_sources(),
+            Err(dotenv::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Config::from_sources()
+            }
+            Err(e) => Err(ConfigError(vec![format!(".env: {e}")])),
+        }
+    }
+
+    /// Loads the config from the file and the environment. `.env` is not
+    /// read again: dotenv never overwrites a variable that is already set,
+    /// so edits to it would be silently ignored.
+    pub fn from_sources() -> Result<Config, ConfigError> {
+        let mut errors = Vec::new();
+
+        let mut config = match config_path() {
+            Some(path) => Config::from_file(&path).unwrap_or_else(|e| {
+                errors.push(e);
+                Config::default()
+            }),
+            None => Config::default(),
+        };
+
+        config.apply_env(&mut errors);
+        config.validate(&mut errors);
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(errors))
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, String> {
+        let raw = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        toml::from_str(&raw).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn is_production(&self) -> bool {
+        self.environment == Environment::Production
+    }
+
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        env_parse("APP_ENV", &mut self.environment, errors);
+        env_parse("BIND_ADDR", &mut self.server.bind_addr, errors);
+        env_parse(
+            "SHUTDOWN_TIMEOUT_SECS",
+            &mut self.server.shutdown_timeout_secs,
+            errors,
+        );
+
+        env_secret("DATABASE_URL", &mut self.database.url);
+        env_parse(
+            "DB_MAX_CONNECTIONS",
+            &mut self.database.max_connections,
+            errors,
+        );
+        env_parse(
+            "DB_MIN_CONNECTIONS",
+            &mut self.database.min_connections,
+            errors,
+        );
+        env_parse(
+            "DB_CONNECT_TIMEOUT",
+            &mut self.database.connect_timeout_secs,
+            errors,
+        );
+        env_parse(
+            "DB_IDLE_TIMEOUT",
+            &mut self.database.idle_timeout_secs,
+            errors,
+        );
+
+        env_parse("TOKEN_FORMAT", &mut self.auth.token_format, errors);
+        env_secret("JWT_SECRET", &mut self.auth.jwt_secret);
+        if let Ok(raw) = std::env::var("JWT_PREVIOUS_SECRETS") {
+            self.auth.previous_jwt_secrets = split_list(&raw).map(Secret::new).collect();
+        }
+        env_secret("PASETO_KEY", &mut self.auth.paseto_key);
+        if let Ok(raw) = std::env::var("PASETO_PREVIOUS_KEYS") {
+            self.auth.previous_paseto_keys = split_list(&raw).map(Secret::new).collect();
+        }
+        env_secret("SESSION_HASH_KEY", &mut self.auth.session_hash_key);
+        env_parse("TOKEN_TTL_SECS", &mut self.auth.token_ttl_secs, errors);
+        env_parse("JWT_ISSUER", &mut self.auth.issuer, errors);
+        env_parse("JWT_AUDIENCE", &mut self.auth.audience, errors);
+        env_parse("JWT_LEEWAY_SECS", &mut self.auth.leeway_secs, errors);
+        if let Ok(raw) = std::env::var("JWT_ALGORITHMS") {
+            match split_list(&raw).map(str::parse).collect() {
+                Ok(algorithms) => self.auth.algorithms = algorithms,
+                Err(e) => errors.push(format!("JWT_ALGORITHMS: {e}")),
+            }
+        }
+
+        env_parse("ADMIN_USERNAME", &mut self.admin.username, errors);
+        env_parse("ADMIN_NAME", &mut self.admin.name, errors);
+        env_parse("ADMIN_SURNAME", &mut self.admin.surname, errors);
+        env_parse("ADMIN_PHONE", &mut self.admin.phone, errors);
+        env_parse("ADMIN_EMAIL", &mut self.admin.email, errors);
+        if let Ok(password) = std::env::var("ADMIN_PASSWORD") {
+            self.admin.password = Some(Secret::new(password));
+        }
+
+        env_parse("LOG_LEVEL", &mut self.log.level, errors);
+        env_parse("LOG_FORMAT", &mut self.log.format, errors);
+        env_parse("LOG_PII", &mut self.log.pii, errors);
+
+        if let Ok(raw) = std::env::var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = split_list(&raw).map(str::to_string).collect();
+        }
+        env_parse(
+            "LOGIN_ATTEMPTS_PER_MINUTE",
+            &mut self.rate_limit.login_attempts_per_minute,
+            errors,
+        );
+
+        env_parse(
+            "SESSION_CACHE_TTL_SECS",
+            &mut self.session_cache.ttl_secs,
+            errors,
+        );
+        env_parse(
+            "SESSION_CACHE_MAX_ENTRIES",
+            &mut self.session_cache.max_entries,
+            errors,
+        );
+
+        env_parse("TLS_ENABLED", &mut self.tls.enabled, errors);
+        env_parse("TLS_CERT_PATH", &mut self.tls.cert_path, errors);
+        env_parse("TLS_KEY_PATH", &mut self.tls.key_path, errors);
+        env_parse_optional(
+            "TLS_REDIRECT_HTTP_ADDR",
+            &mut self.tls.redirect_http_addr,
+            errors,
+        );
+        if let Ok(raw) = std::env::var("TLS_CLIENT_CA_PATHS") {
+            self.tls.client_ca_paths = split_list(&raw).map(PathBuf::from).collect();
+        }
+        env_parse(
+            "TLS_REQUIRE_CLIENT_CERT",
+            &mut self.tls.require_client_cert,
+            errors,
+        );
+
+        env_parse("METRICS_ENABLED", &mut self.metrics.enabled, errors);
+        env_parse_optional("METRICS_ADMIN_ADDR", &mut self.metrics.admin_addr, errors);
+
+        env_parse_optional(
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+            &mut self.tracing.otlp_endpoint,
+            errors,
+        );
+        env_parse_optional("TRACING_FILE_PATH", &mut self.tracing.file_path, errors);
+        env_parse("OTEL_SERVICE_NAME", &mut self.tracing.service_name, errors);
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        let url = self.database.url.expose();
+        if url.is_empty() {
+            errors.push("database.url (DATABASE_URL) is required".to_string());
+        } else if url == MEMORY_URL {
+            if self.is_production() {
+                errors.push(format!(
+                    "database.url (DATABASE_URL) {MEMORY_URL} is not allowed in production"
+                ));
+            }
+        } else if !url.starts_with("postgres://")
+            && !url.starts_with("postgresql://")
+            && !url.starts_with(SQLITE_SCHEME)
+        {
+            errors.push(format!(
+                "database.url (DATABASE_URL) must be a postgres:// or {SQLITE_SCHEME} URL, or {MEMORY_URL}"
+            ));
+        }
+        if self.database.max_connections == 0 {
+            errors.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            errors.push(format!(
+                "database.min_connections ({}) must not exceed database.max_connections ({})",
+                self.database.min_connections, self.database.max_connections
+            ));
+        }
+        if self.database.connect_timeout_secs == 0 {
+            errors.push("database.connect_timeout_secs must be at least 1".to_string());
+        }
+
+        match self.auth.token_format {
+            TokenFormat::Jwt if self.auth.jwt_secret.is_empty() => {
+                errors.push("auth.jwt_secret (JWT_SECRET) is required".to_string());
+            }
+            TokenFormat::Jwt if self.auth.jwt_secret.expose().len() < MIN_JWT_SECRET_LEN => {
+                errors.push(format!(
+                    "auth.jwt_secret (JWT_SECRET) must be at least {MIN_JWT_SECRET_LEN} bytes"
+                ));
+            }
+            TokenFormat::PasetoV4Local | TokenFormat::PasetoV4Public
+                if self.auth.paseto_key.is_empty() =>
+            {
+                errors.push(
+                    "auth.paseto_key (PASETO_KEY) is required for the paseto token formats"
+                        .to_string(),
+                );
+            }
+            _ => {}
+        }
+        if !self.auth.paseto_keys().all(is_paseto_key) {
+            errors.push(
+                "auth.paseto_key and auth.previous_paseto_keys must be 64 hex characters (32 bytes)"
+                    .to_string(),
+            );
+        }
+        if self
+            .auth
+            .previous_jwt_secrets
+            .iter()
+            .any(|s| s.expose().len() < MIN_JWT_SECRET_LEN)
+        {
+            errors.push(format!(
+                "auth.previous_jwt_secrets entries must be at least {MIN_JWT_SECRET_LEN} bytes"
+            ));
+        }
+        if self.auth.session_hash_key.is_empty() {
+            errors.push("auth.session_hash_key (SESSION_HASH_KEY) is required".to_string());
+        } else if self.auth.session_hash_key.expose().len() < MIN_JWT_SECRET_LEN {
+            errors.push(format!(
+                "auth.session_hash_key (SESSION_HASH_KEY) must be at least {MIN_JWT_SECRET_LEN} bytes"
+            ));
+        }
+        if self.auth.token_ttl_secs == 0 || self.auth.token_ttl_secs > 7 * 24 * 60 * 60 {
+            errors.push("auth.token_ttl_secs must be between 1 second and 7 days".to_string());
+        }
+        if self.auth.issuer.trim().is_empty() {
+            errors.push("auth.issuer (JWT_ISSUER) must not be empty".to_string());
+        }
+        if self.auth.audience.trim().is_empty() {
+            errors.push("auth.audience (JWT_AUDIENCE) must not be empty".to_string());
+        }
+        if self.auth.leeway_secs > 5 * 60 {
+            errors.push("auth.leeway_secs (JWT_LEEWAY_SECS) must be at most 300".to_string());
+        }
+        if self.auth.algorithms.is_empty() {
+            errors.push(
+                "auth.algorithms (JWT_ALGORITHMS) must list at least one algorithm".to_string(),
+            );
+        } else if let Some(alg) = self
+            .auth
+            .algorithms
+            .iter()
+            .find(|alg| !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512))
+        {
+            errors.push(format!(
+                "auth.algorithms (JWT_ALGORITHMS): {alg:?} is not supported, tokens are signed with shared secrets (HS256, HS384, HS512)"
+            ));
+        }
+        if self.session_cache.ttl_secs > 300 {
+            errors.push("session_cache.ttl_secs must be at most 300 seconds".to_string());
+        }
+        if self.session_cache.ttl_secs > 0 && self.session_cache.max_entries == 0 {
+            errors.push(
+                "session_cache.max_entries must be at least 1 while the cache is enabled"
+                    .to_string(),
+            );
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.log.level) {
+            errors.push(format!(
+                "log.level '{}' is not a valid filter: {e}",
+                self.log.level
+            ));
+        }
+
+        if self.is_production() && self.log.pii != PiiPolicy::Redact {
+            errors.push("log.pii (LOG_PII) must be 'redact' in production".to_string());
+        }
+
+        for origin in &self.cors.allowed_origins {
+            let scheme_ok = origin.starts_with("http://") || origin.starts_with("https://");
+            if !scheme_ok || origin.ends_with('/') || HeaderValue::from_str(origin).is_err() {
+                errors.push(format!(
+                    "cors.allowed_origins entry '{origin}' must look like https://host[:port]"
+                ));
+            }
+        }
+
+        if self.tls.enabled {
+            for (key, path) in [
+                ("tls.cert_path (TLS_CERT_PATH)", &self.tls.cert_path),
+                ("tls.key_path (TLS_KEY_PATH)", &self.tls.key_path),
+            ] {
+                if path.as_os_str().is_empty() {
+                    errors.push(format!("{key} is required when TLS is enabled"));
+                } else if !path.is_file() {
+                    errors.push(format!("{key} '{}' is not a readable file", path.display()));
+                }
+            }
+            if self.tls.redirect_http_addr == Some(self.server.bind_addr) {
+                errors.push("tls.redirect_http_addr must differ from server.bind_addr".to_string());
+            }
+        } else if self.tls.redirect_http_addr.is_some() {
+            errors.push("tls.redirect_http_addr requires tls.enabled".to_string());
+        }
+        if !self.tls.client_ca_paths.is_empty() && !self.tls.enabled {
+            errors.push("tls.client_ca_paths requires tls.enabled".to_string());
+        }
+        for path in &self.tls.client_ca_paths {
+            if !path.is_file() {
+                errors.push(format!(
+                    "tls.client_ca_paths entry '{}' is not a readable file",
+                    path.display()
+                ));
+            }
+        }
+        if self.tls.require_client_cert && self.tls.client_ca_paths.is_empty() {
+            errors.push("tls.require_client_cert requires tls.client_ca_paths".to_string());
+        }
+
+        if let Some(admin_addr) = self.metrics.admin_addr {
+            if !self.metrics.enabled {
+                errors.push("metrics.admin_addr requires metrics.enabled".to_string());
+            }
+            if admin_addr == self.server.bind_addr
+                || Some(admin_addr) == self.tls.redirect_http_addr
+            {
+                errors.push(
+                    "metrics.admin_addr must differ from the other listen addresses".to_string(),
+                );
+            }
+        }
+
+        if let Some(endpoint) = &self.tracing.otlp_endpoint {
+            if self.tracing.file_path.is_some() {
+                errors.push(
+                    "tracing.otlp_endpoint and tracing.file_path cannot both be set".to_string(),
+                );
+            }
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                errors.push(format!(
+                    "tracing.otlp_endpoint '{endpoint}' must be an http:// or https:// URL"
+                ));
+            }
+        }
+        if self.tracing.service_name.is_empty() {
+            errors.push("tracing.service_name (OTEL_SERVICE_NAME) cannot be empty".to_string());
+        }
+
+        let mut subjects = HashSet::new();
+        for principal in &self.service_principals {
+            if principal.name.is_empty() || principal.subject.is_empty() {
+                errors.push("service_principals entries need a name and a subject".to_string());
+            } else if !subjects.insert(&principal.subject) {
+                errors.push(format!(
+                    "service_principals subject '{}' is listed more than once",
+                    principal.subject
+                ));
+            }
+        }
+    }
+
+    pub fn service_principal(&self, subject: &str) -> Option<&ServicePrincipal> {
+        self.service_principals
+            .iter()
+            .find(|p| p.subject == subject)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.server.shutdown_timeout_secs)
+    }
+
+    pub fn db_connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.database.connect_timeout_secs)
+    }
+
+    pub fn db_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.database.idle_timeout_secs)
+    }
+}
+
+fn is_paseto_key(key: &Secret) -> bool {
+    key.is_empty() || hex::decode(key.expose()).is_ok_and(|bytes| bytes.len() == 32)
+}
+
+fn split_list(raw: &str) -> impl Iterator<Item = &str> {
+    raw.split(',').map(str::trim).filter(|v| !v.is_empty())
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    match std::env::var(CONFIG_PATH_ENV) {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => {
+            let default = PathBuf::from(DEFAULT_CONFIG_PATH);
+            default.exists().then_some(default)
+        }
+    }
+}
+
+fn env_parse<T>(key: &str, target: &mut T, errors: &mut Vec<String>)
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let Ok(raw) = std::env::var(key) else {
+        return;
+    };
+    match raw.parse() {
+        Ok(value) => *target = value,
+        Err(e) => errors.push(format!("{key}: {e}")),
+    }
+}
+
+/// Like `env_parse`, but an empty value clears the setting.
+fn env_parse_optional<T>(key: &str, target: &mut Option<T>, errors: &mut Vec<String>)
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let Ok(raw) = std::env::var(key) else {
+        return;
+    };
+    match raw.trim() {
+        "" => *target = None,
+        value => match value.parse() {
+            Ok(value) => *target = Some(value),
+            Err(e) => errors.push(format!("{key}: {e}")),
+        },
+    }
+}
+
+fn env_secret(key: &str, target: &mut Secret) {
+    if let Ok(value) = std::env::var(key) {
+        *target = Secret::new(value);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::{Config, config_path};
+use crate::shutdown::Shutdown;
+use crate::telemetry::redact;
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub type LogLevelHandle = reload::Handle<EnvFilter, Registry>;
+
+/// The live `Config`. Each request loads a snapshot, so a reload applies to
+/// requests that start after it and never changes one in flight.
+#[derive(Clone)]
+pub struct SharedConfig(Arc<ArcSwap<Config>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> SharedConfig {
+        SharedConfig(Arc::new(ArcSwap::from_pointee(config)))
+    }
+
+    pub fn load(&self) -> Arc<Config> {
+        self.0.load_full()
+    }
+
+    /// Re-reads the config file and environment and swaps the result in if
+    /// it is valid and leaves startup-only settings untouched. The running
+    /// config is kept on any error.
+    pub fn reload(&self, log_level: &LogLevelHandle, trigger: &str) {
+        let candidate = match Config::from_sources() {
+            Ok(c) => c,
+            Err(e) => {
+                error!("CONFIG: Reload ({trigger}) rejected, keeping current config: {e}");
+                return;
+            }
+        };
+
+        let current = self.load();
+        let startup_only = current.startup_only_changes(&candidate);
+        if !startup_only.is_empty() {
+            error!(
+                "CONFIG: Reload ({trigger}) rejected, {} cannot change at runtime; restart to apply",
+                startup_only.join(", ")
+            );
+            return;
+        }
+        if *current == candidate {
+            info!("CONFIG: Reload ({trigger}) found no changes");
+            return;
+        }
+
+        if current.log.level != candidate.log.level
+            && let Err(e) = log_level.reload(EnvFilter::new(&candidate.log.level))
+        {
+            error!("CONFIG: Reload ({trigger}) could not apply log level: {e}");
+            return;
+        }
+
+        redact::set_policy(candidate.log.pii);
+        let changed = changed_sections(&current, &candidate);
+        self.0.store(Arc::new(candidate));
+        info!(
+            "CONFIG: Reload ({trigger}) applied, changed: {}",
+            changed.join(", ")
+        );
+    }
+}
+
+fn changed_sections(old: &Config, new: &Config) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if old.auth != new.auth {
+        changed.push("auth");
+    }
+    if old.log != new.log {
+        changed.push("log");
+    }
+    if old.cors != new.cors {
+        changed.push("cors");
+    }
+    if old.rate_limit != new.rate_limit {
+        changed.push("rate_limit");
+    }
+    if old.service_principals != new.service_principals {
+        changed.push("service_principals");
+    }
+    changed
+}
+
+/// Reloads on SIGHUP and whenever the config file's modification time
+/// changes. Values set through environment variables keep overriding the
+/// file, exactly as at startup.
+pub fn spawn_reloader(config: SharedConfig, log_level: LogLevelHandle, shutdown: &Shutdown) {
+    shutdown.spawn(async move {
+        let path = config_path();
+        let mut last_modified = path.as_deref().and_then(modified);
+        let mut poll = tokio::time::interval(FILE_POLL_INTERVAL);
+        #[cfg(unix)]
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("CONFIG: SIGHUP reload unavailable: {e}");
+                None
+            }
+        };
+
+        loop {
+            #[cfg(unix)]
+            let sighup = async {
+                match hangup.as_mut() {
+                    Some(s) => s.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            #[cfg(not(unix))]
+            let sighup = std::future::pending::<Option<()>>();
+
+            tokio::select! {
+                _ = sighup => config.reload(&log_level, "SIGHUP"),
+                _ = poll.tick() => {
+                    let current = path.as_deref().and_then(modified);
+                    if current != last_modified {
+                        last_modified = current;
+                        config.reload(&log_level, "file change");
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn modified(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
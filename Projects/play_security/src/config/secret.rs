@@ -0,0 +1,32 @@
+use serde::Deserialize;
+use std::fmt;
+
+/// String that never shows up in `Debug` output, so a `Config` can be
+/// logged as a whole.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Secret {
+        Secret(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "Secret(<empty>)")
+        } else {
+            write!(f, "Secret(<redacted>)")
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::auth::password::{hash_password, verify_password};
+use crate::database::errors::DbError;
+use crate::database::repository::UserRepository;
+use crate::telemetry::redact;
+use crate::validation::{PHONE_RE, USERNAME_RE, field_errors, validate_password_bytes};
+use rand::RngCore;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use subtle::ConstantTimeEq;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Passwords that ship in the source or are trivially guessed. `{username}#01!`
+/// is also what `create_user` hands out, so it is checked per account.
+const KNOWN_DEFAULT_PASSWORDS: &[&str] = &["admin#01!", "admin", "password", "changeme"];
+
+/// Credentials for the first admin, from `[admin]` config or `POST /setup`.
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct AdminAccount {
+    #[validate(
+        length(min = 3, max = 50, code = "length", message = "username must be 3 to 50 characters"),
+        regex(path = *USERNAME_RE, code = "invalid_username", message = "username may only contain letters, digits, '.', '_' and '-'")
+    )]
+    pub username: String,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        code = "length",
+        message = "name must be 1 to 50 characters"
+    ))]
+    pub name: String,
+    #[validate(length(
+        min = 1,
+        max = 70,
+        code = "length",
+        message = "surname must be 1 to 70 characters"
+    ))]
+    pub surname: String,
+    #[validate(regex(path = *PHONE_RE, code = "invalid_phone", message = "phone must be in E.164 format, e.g. +27821234567"))]
+    pub phone: String,
+    #[validate(
+        email(code = "invalid_email", message = "email must be a valid address"),
+        length(
+            max = 100,
+            code = "length",
+            message = "email must be at most 100 characters"
+        )
+    )]
+    pub email: String,
+    #[validate(
+        length(min = 12, code = "length", message = "password must be at least 12 characters"),
+        custom(function = validate_password_bytes)
+    )]
+    pub password: String,
+}
+
+impl fmt::Debug for AdminAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminAccount")
+            .field("username", &self.username)
+            .field("name", &redact::name(&self.name))
+            .field("surname", &redact::name(&self.surname))
+            .field("phone", &redact::phone(&self.phone))
+            .field("email", &redact::email(&self.email))
+            .field("password", &redact::Hidden)
+            .finish()
+    }
+}
+
+impl AdminAccount {
+    pub fn uses_known_default(&self) -> bool {
+        is_known_default(&self.username, &self.password)
+    }
+}
+
+fn is_known_default(username: &str, password: &str) -> bool {
+    KNOWN_DEFAULT_PASSWORDS.contains(&password) || password == format!("{username}#01!")
+}
+
+/// One-time token printed to the log when no admin exists and none is
+/// configured. Consumed by the first successful `POST /setup`.
+#[derive(Clone)]
+pub struct SetupToken(Arc<Mutex<Option<String>>>);
+
+impl SetupToken {
+    fn generate() -> SetupToken {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        SetupToken(Arc::new(Mutex::new(Some(hex::encode(bytes)))))
+    }
+
+    fn value(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn matches(&self, candidate: &str) -> bool {
+        match self.0.lock().unwrap().as_deref() {
+            Some(token) => bool::from(token.as_bytes().ct_eq(candidate.as_bytes())),
+            None => false,
+        }
+    }
+
+    pub fn consume(&self) {
+        self.0.lock().unwrap().take();
+    }
+}
+
+pub enum BootstrapOutcome {
+    AlreadyBootstrapped,
+    Created,
+    AwaitingSetup(SetupToken),
+}
+
+#[derive(Debug)]
+pub enum BootstrapError {
+    Invalid(String),
+    DefaultPassword(String),
+    Hash(bcrypt::BcryptError),
+    Db(DbError),
+}
+
+impl fmt::Display for BootstrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootstrapError::Invalid(msg) => write!(f, "invalid admin configuration: {msg}"),
+            BootstrapError::DefaultPassword(user) => write!(
+                f,
+                "admin account '{user}' uses a known default password, which is not allowed in production"
+            ),
+            BootstrapError::Hash(e) => write!(f, "password hashing failed: {e}"),
+            BootstrapError::Db(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<DbError> for BootstrapError {
+    fn from(err: DbError) -> Self {
+        BootstrapError::Db(err)
+    }
+}
+
+/// Makes sure an admin can log in without ever seeding a well-known
+/// password. Runs on every startup but only creates an account while no
+/// admin exists.
+pub async fn bootstrap_admin(
+    users: &dyn UserRepository,
+    configured: Option<AdminAccount>,
+    production: bool,
+) -> Result<BootstrapOutcome, BootstrapError> {
+    if production {
+        refuse_default_admin_passwords(users).await?;
+    }
+
+    if users.admin_exists().await? {
+        info!("BOOTSTRAP: Admin account already exists, skipping");
+        return Ok(BootstrapOutcome::AlreadyBootstrapped);
+    }
+
+    let Some(account) = configured else {
+        let token = SetupToken::generate();
+        warn!(
+            "BOOTSTRAP: No admin account exists. Create one with POST /setup and header X-Setup-Token: {}",
+            token.value().unwrap_or_default()
+        );
+        return Ok(BootstrapOutcome::AwaitingSetup(token));
+    };
+
+    if let Err(e) = account.validate() {
+        let fields: Vec<String> = field_errors(&e).into_iter().map(|f| f.message).collect();
+        return Err(BootstrapError::Invalid(fields.join("; ")));
+    }
+    if account.uses_known_default() {
+        if production {
+            return Err(BootstrapError::DefaultPassword(account.username));
+        }
+        warn!("BOOTSTRAP: ADMIN_PASSWORD is a known default; this is refused in production");
+    }
+
+    if create_first_admin(users, &account).await? {
+        info!("BOOTSTRAP: Created admin account '{}'", account.username);
+        Ok(BootstrapOutcome::Created)
+    } else {
+        Ok(BootstrapOutcome::AlreadyBootstrapped)
+    }
+}
+
+/// Creates the admin only while no other admin exists, so concurrent
+/// instances or a replayed setup request cannot create a second one.
+/// Returns `false` when another admin won the race.
+pub async fn create_first_admin(
+    users: &dyn UserRepository,
+    account: &AdminAccount,
+) -> Result<bool, BootstrapError> {
+    let hashed = hash_password(&account.password).map_err(BootstrapError::Hash)?;
+    Ok(users.create_first_admin(account, &hashed).await?)
+}
+
+/// Checks every admin against the same list as `is_known_default`.
+async fn refuse_default_admin_passwords(users: &dyn UserRepository) -> Result<(), BootstrapError> {
+    for (username, pwd) in users.admin_credentials().await? {
+        let legacy_default = format!("{username}#01!");
+        let uses_default = std::iter::once(legacy_default.as_str())
+            .chain(KNOWN_DEFAULT_PASSWORDS.iter().copied())
+            .any(|candidate| verify_password(candidate, &pwd).unwrap_or(false));
+        if uses_default {
+            return Err(BootstrapError::DefaultPassword(username));
+        }
+    }
+    Ok(())
+}
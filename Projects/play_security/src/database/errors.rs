@@ -0,0 +1,97 @@
+use sqlx::error::{DatabaseError, ErrorKind};
+use sqlx::postgres::PgDatabaseError;
+use std::fmt;
+
+/// Postgres SQLSTATE for values longer than their `VARCHAR` column.
+const STRING_DATA_RIGHT_TRUNCATION: &str = "22001";
+
+/// Domain view of a failed query. Constraint violations carry enough detail
+/// for the client to fix the request; everything else keeps the source error
+/// so it can be logged.
+#[derive(Debug)]
+pub enum DbError {
+    UniqueViolation { field: String },
+    ForeignKeyViolation { constraint: String },
+    CheckViolation { constraint: String },
+    NotNullViolation { field: String },
+    ValueTooLong,
+    Unexpected(sqlx::Error),
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(err: sqlx::Error) -> Self {
+        let Some(db_err) = err.as_database_error() else {
+            return DbError::Unexpected(err);
+        };
+
+        let constraint = db_err.constraint().unwrap_or_default().to_string();
+        match db_err.kind() {
+            ErrorKind::UniqueViolation => DbError::UniqueViolation {
+                field: conflicting_field(db_err),
+            },
+            ErrorKind::ForeignKeyViolation => DbError::ForeignKeyViolation { constraint },
+            ErrorKind::CheckViolation => DbError::CheckViolation { constraint },
+            ErrorKind::NotNullViolation => DbError::NotNullViolation {
+                field: db_err
+                    .try_downcast_ref::<PgDatabaseError>()
+                    .and_then(|e| e.column())
+                    .or_else(|| sqlite_column(db_err))
+                    .unwrap_or_default()
+                    .to_string(),
+            },
+            _ if db_err.code().as_deref() == Some(STRING_DATA_RIGHT_TRUNCATION) => {
+                DbError::ValueTooLong
+            }
+            _ => DbError::Unexpected(err),
+        }
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::UniqueViolation { field } => write!(f, "unique violation on {field}"),
+            DbError::ForeignKeyViolation { constraint } => {
+                write!(f, "foreign key violation on {constraint}")
+            }
+            DbError::CheckViolation { constraint } => write!(f, "check violation on {constraint}"),
+            DbError::NotNullViolation { field } => write!(f, "not null violation on {field}"),
+            DbError::ValueTooLong => write!(f, "value too long for column"),
+            DbError::Unexpected(e) => write!(f, "unexpected database error: {e}"),
+        }
+    }
+}
+
+/// Postgres reports unique violations as `Key (email)=(x@y.z) already exists.`
+/// and SQLite in the error message; fall back to the default
+/// `<table>_<column>_key` constraint name.
+fn conflicting_field(db_err: &dyn DatabaseError) -> String {
+    let from_detail = db_err
+        .try_downcast_ref::<PgDatabaseError>()
+        .and_then(|e| e.detail())
+        .and_then(|d| d.strip_prefix("Key ("))
+        .and_then(|d| d.split_once(")="))
+        .map(|(cols, _)| cols.to_string())
+        .or_else(|| sqlite_column(db_err).map(str::to_string));
+
+    from_detail.unwrap_or_else(|| {
+        let constraint = db_err.constraint().unwrap_or_default();
+        let trimmed = constraint.strip_suffix("_key").unwrap_or(constraint);
+        match (db_err.table(), trimmed) {
+            (Some(table), c) => c
+                .strip_prefix(table)
+                .and_then(|c| c.strip_prefix('_'))
+                .unwrap_or(c)
+                .to_string(),
+            (None, c) => c.to_string(),
+        }
+    })
+}
+
+/// SQLite names the column only in the message, as
+/// `UNIQUE constraint failed: users.email`.
+fn sqlite_column(db_err: &dyn DatabaseError) -> Option<&str> {
+    let (_, columns) = db_err.message().split_once(" constraint failed: ")?;
+    let first = columns.split(", ").next()?;
+    Some(first.rsplit_once('.').map_or(first, |(_, column)| column))
+}
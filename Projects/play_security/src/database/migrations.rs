@@ -0,0 +1,173 @@
+use sqlx::error::DatabaseError;
+use sqlx::migrate::{AppliedMigration, Migrate, MigrateError, Migrator};
+use sqlx::{Executor, FromRow, IntoArguments, Pool, Postgres, Sqlite};
+use tracing::{error, info};
+
+/// Migrations under `migrations/<backend>/`, embedded at compile time. Both
+/// sets use the same versions. Applied versions and their checksums are
+/// tracked in `_sqlx_migrations`.
+pub static POSTGRES: Migrator = sqlx::migrate!("./migrations/postgres");
+pub static SQLITE: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// What `list_applied_migrations` reads, without first creating the table.
+const APPLIED_MIGRATIONS: &str = "SELECT version, checksum FROM _sqlx_migrations ORDER BY version";
+
+/// A database with its own migration set.
+pub trait Migrations: sqlx::Database<Connection: Migrate> {
+    fn migrator() -> &'static Migrator;
+}
+
+impl Migrations for Postgres {
+    fn migrator() -> &'static Migrator {
+        &POSTGRES
+    }
+}
+
+impl Migrations for Sqlite {
+    fn migrator() -> &'static Migrator {
+        &SQLITE
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+pub async fn applied_migrations<DB: Migrations>(
+    pool: &Pool<DB>,
+) -> Result<Vec<AppliedMigration>, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    conn.list_applied_migrations().await
+}
+
+/// Fails when the database has a migration this binary does not know about,
+/// i.e. it was migrated by a newer release, or one applied from another
+/// application's migration set.
+pub async fn ensure_compatible<DB: Migrations>(pool: &Pool<DB>) -> Result<(), MigrateError> {
+    let latest_known = DB::migrator().iter().map(|m| m.version).max().unwrap_or(0);
+    let applied = applied_migrations(pool).await?;
+    if let Some(foreign) = foreign_migration::<DB>(&applied) {
+        error!(
+            "MIGRATIONS: Migration {} was applied with a different checksum, so this database was migrated by another application; play_security needs a database of its own",
+            foreign.version
+        );
+        return Err(MigrateError::VersionMismatch(foreign.version));
+    }
+    if let Some(unknown) = applied
+        .iter()
+        .find(|m| !DB::migrator().version_exists(m.version))
+    {
+        error!(
+            "MIGRATIONS: Database schema is at version {} but this binary only knows up to {}; refusing to start",
+            unknown.version, latest_known
+        );
+        return Err(MigrateError::VersionMissing(unknown.version));
+    }
+    Ok(())
+}
+
+/// An applied migration whose version this binary ships with different
+/// contents. `_sqlx_migrations` holds one set per database, and first_axum's
+/// migrations reuse these versions, so the two apps cannot share one.
+fn foreign_migration<DB: Migrations>(applied: &[AppliedMigration]) -> Option<&AppliedMigration> {
+    applied.iter().find(|a| {
+        DB::migrator()
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .any(|m| m.version == a.version && m.checksum != a.checksum)
+    })
+}
+
+pub async fn run<DB: Migrations>(pool: &Pool<DB>) -> Result<(), MigrateError> {
+    ensure_compatible(pool).await?;
+    for pending in status(pool).await?.iter().filter(|m| !m.applied) {
+        info!(
+            "MIGRATIONS: Applying {} {}",
+            pending.version, pending.description
+        );
+    }
+    DB::migrator().run(pool).await
+}
+
+/// Reverts every applied migration newer than `target`.
+pub async fn rollback<DB: Migrations>(pool: &Pool<DB>, target: i64) -> Result<(), MigrateError> {
+    ensure_compatible(pool).await?;
+    info!("MIGRATIONS: Rolling back to version {}", target);
+    DB::migrator().undo(pool, target).await
+}
+
+pub async fn status<DB: Migrations>(pool: &Pool<DB>) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let applied = applied_migrations(pool).await?;
+    Ok(DB::migrator()
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            applied: applied.iter().any(|a| a.version == m.version),
+        })
+        .collect())
+}
+
+/// Every migration this binary ships is applied and the database has none
+/// it does not know about. Runs on every readiness probe, so it only reads
+/// `_sqlx_migrations` and needs no DDL rights; a database without the table
+/// has not been migrated and is not ready.
+pub async fn check<DB: Migrations>(pool: &Pool<DB>) -> Result<(), String>
+where
+    for<'c> &'c Pool<DB>: Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    (i64, Vec<u8>): for<'r> FromRow<'r, DB::Row>,
+{
+    let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(APPLIED_MIGRATIONS)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if is_missing_table(db.as_ref()) => {
+                "migrations table _sqlx_migrations does not exist".to_string()
+            }
+            e => e.to_string(),
+        })?;
+    let applied: Vec<AppliedMigration> = rows
+        .into_iter()
+        .map(|(version, checksum)| AppliedMigration {
+            version,
+            checksum: checksum.into(),
+        })
+        .collect();
+    if let Some(foreign) = foreign_migration::<DB>(&applied) {
+        return Err(format!(
+            "migration {} was applied from another migration set",
+            foreign.version
+        ));
+    }
+    if let Some(unknown) = applied
+        .iter()
+        .find(|m| !DB::migrator().version_exists(m.version))
+    {
+        return Err(format!(
+            "database has migration {} which this binary does not know",
+            unknown.version
+        ));
+    }
+    match DB::migrator()
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .find(|m| !applied.iter().any(|a| a.version == m.version))
+    {
+        Some(pending) => Err(format!(
+            "migration {} {} is pending",
+            pending.version, pending.description
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Undefined table: SQLSTATE 42P01 on Postgres, a plain message on SQLite.
+fn is_missing_table(err: &dyn DatabaseError) -> bool {
+    err.code().as_deref() == Some("42P01") || err.message().starts_with("no such table")
+}
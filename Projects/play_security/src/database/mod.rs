@@ -1,34 +1,110 @@
-pub mod models;
+pub mod bootstrap;
+pub mod errors;
+pub mod migrations;
+pub mod repository;
 
-use sqlx::{PgPool, postgres::PgPoolOptions};
-use std::time::Duration;
+use crate::config::Config;
+use repository::{MemoryStore, PgStore, Readiness, SessionRepository, SqliteStore, UserRepository};
+use sqlx::migrate::MigrateError;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{PgPool, SqlitePool};
+use std::str::FromStr;
+use std::sync::Arc;
 
-pub async fn create_pool(database_url: &str) -> Result<PgPool, sqlx::Error> {
-    let max_connections: u32 = std::env::var("DB_MAX_CONNECTIONS")
-        .unwrap_or_else(|_| "5".to_string())
-        .parse()
-        .unwrap_or(5);
+/// `database.url` value that selects `MemoryStore`.
+pub const MEMORY_URL: &str = "memory://";
 
-    let min_connections: u32 = std::env::var("DB_MIN_CONNECTIONS")
-        .unwrap_or_else(|_| "1".to_string())
-        .parse()
-        .unwrap_or(1);
+/// `database.url` prefix that selects `SqliteStore`, e.g.
+/// `sqlite://data/play.db` or `sqlite::memory:`.
+pub const SQLITE_SCHEME: &str = "sqlite:";
 
-    let connect_timeout: u64 = std::env::var("DB_CONNECT_TIMEOUT")
-        .unwrap_or_else(|_| "30".to_string())
-        .parse()
-        .unwrap_or(30);
+/// The storage backend, chosen by the scheme of `database.url`.
+#[derive(Clone)]
+pub enum Database {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+    Memory(Arc<MemoryStore>),
+}
+
+impl Database {
+    pub async fn connect(config: &Config) -> Result<Database, sqlx::Error> {
+        let url = config.database.url.expose();
+        if url == MEMORY_URL {
+            return Ok(Database::Memory(Arc::default()));
+        }
+        if url.starts_with(SQLITE_SCHEME) {
+            return create_sqlite_pool(config).await.map(Database::Sqlite);
+        }
+        create_pool(config).await.map(Database::Postgres)
+    }
+
+    pub async fn migrate(&self) -> Result<(), MigrateError> {
+        match self {
+            Database::Postgres(pool) => migrations::run(pool).await,
+            Database::Sqlite(pool) => migrations::run(pool).await,
+            Database::Memory(_) => Ok(()),
+        }
+    }
+
+    pub fn users(&self) -> Arc<dyn UserRepository> {
+        match self {
+            Database::Postgres(pool) => Arc::new(PgStore::new(pool.clone())),
+            Database::Sqlite(pool) => Arc::new(SqliteStore::new(pool.clone())),
+            Database::Memory(store) => store.clone(),
+        }
+    }
+
+    pub fn sessions(&self) -> Arc<dyn SessionRepository> {
+        match self {
+            Database::Postgres(pool) => Arc::new(PgStore::new(pool.clone())),
+            Database::Sqlite(pool) => Arc::new(SqliteStore::new(pool.clone())),
+            Database::Memory(store) => store.clone(),
+        }
+    }
 
-    let idle_timeout: u64 = std::env::var("DB_IDLE_TIMEOUT")
-        .unwrap_or_else(|_| "600".to_string())
-        .parse()
-        .unwrap_or(600);
+    pub fn readiness(&self) -> Arc<dyn Readiness> {
+        match self {
+            Database::Postgres(pool) => Arc::new(PgStore::new(pool.clone())),
+            Database::Sqlite(pool) => Arc::new(SqliteStore::new(pool.clone())),
+            Database::Memory(store) => store.clone(),
+        }
+    }
 
+    pub async fn close(&self) {
+        match self {
+            Database::Postgres(pool) => pool.close().await,
+            Database::Sqlite(pool) => pool.close().await,
+            Database::Memory(_) => {}
+        }
+    }
+}
+
+async fn create_pool(config: &Config) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new()
-        .max_connections(max_connections)
-        .min_connections(min_connections)
-        .acquire_timeout(Duration::from_secs(connect_timeout))
-        .idle_timeout(Duration::from_secs(idle_timeout))
-        .connect(database_url)
+        .max_connections(config.database.max_connections)
+        .min_connections(config.database.min_connections)
+        .acquire_timeout(config.db_connect_timeout())
+        .idle_timeout(config.db_idle_timeout())
+        .connect(config.database.url.expose())
         .await
 }
+
+async fn create_sqlite_pool(config: &Config) -> Result<SqlitePool, sqlx::Error> {
+    let url = config.database.url.expose();
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+    let pool = SqlitePoolOptions::new().acquire_timeout(config.db_connect_timeout());
+    // Every connection to an in-memory database opens a new, empty one, so
+    // keep exactly one connection open for the life of the pool.
+    let pool = if url.contains(":memory:") || url.contains("mode=memory") {
+        pool.max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        pool.max_connections(config.database.max_connections)
+            .min_connections(config.database.min_connections)
+            .idle_timeout(config.db_idle_timeout())
+    };
+    pool.connect_with(options).await
+}
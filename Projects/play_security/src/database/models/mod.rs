@@ -1,3 +0,0 @@
-pub mod table_creation;
-pub mod tables;
-pub use tables::Tables;
@@ -1,2 +0,0 @@
-pub mod user_login;
-pub mod users;
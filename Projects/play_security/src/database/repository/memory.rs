@@ -0,0 +1,327 @@
+use super::{ActiveSession, Readiness, SessionRepository, UserRepository};
+use crate::database::bootstrap::AdminAccount;
+use crate::database::errors::DbError;
+use crate::routes::login::UserData as LoginUser;
+use crate::routes::users::UserData;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Process-local storage selected with `database.url = "memory://"`, so the
+/// API can run without a database. Enforces the same unique columns as the
+/// `users` table; nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    users: Mutex<Users>,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+#[derive(Default)]
+struct Users {
+    rows: BTreeMap<i32, UserRow>,
+    last_id: i32,
+}
+
+struct UserRow {
+    username: String,
+    name: String,
+    surname: String,
+    phone: String,
+    email: String,
+    pwd: String,
+    create_date: NaiveDateTime,
+    created_by: String,
+    write_date: Option<NaiveDateTime>,
+    update_by: Option<String>,
+    active: bool,
+    is_admin: bool,
+}
+
+struct Session {
+    token_hash: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl UserRow {
+    fn view(&self, id: i32) -> UserData {
+        UserData {
+            id,
+            username: self.username.clone(),
+            name: self.name.clone(),
+            surname: self.surname.clone(),
+            phone: self.phone.clone(),
+            email: self.email.clone(),
+            create_date: self.create_date,
+            created_by: self.created_by.clone(),
+            write_date: self.write_date,
+            update_by: self.update_by.clone(),
+        }
+    }
+
+    fn credentials(&self) -> LoginUser {
+        LoginUser {
+            username: self.username.clone(),
+            name: self.name.clone(),
+            surname: self.surname.clone(),
+            phone: self.phone.clone(),
+            email: self.email.clone(),
+            pwd: self.pwd.clone(),
+            active: Some(self.active),
+        }
+    }
+}
+
+impl Users {
+    /// Mirrors the `UNIQUE` constraints on `users`, ignoring row `skip`.
+    fn check_unique(
+        &self,
+        skip: Option<i32>,
+        username: &str,
+        phone: &str,
+        email: &str,
+    ) -> Result<(), DbError> {
+        for (id, row) in &self.rows {
+            if Some(*id) == skip {
+                continue;
+            }
+            let field = if row.username == username {
+                "username"
+            } else if row.phone == phone {
+                "phone"
+            } else if row.email == email {
+                "email"
+            } else {
+                continue;
+            };
+            return Err(DbError::UniqueViolation {
+                field: field.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, row: UserRow) {
+        self.last_id += 1;
+        self.rows.insert(self.last_id, row);
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryStore {
+    async fn list(&self) -> Result<Vec<UserData>, DbError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.rows.iter().map(|(id, row)| row.view(*id)).collect())
+    }
+
+    async fn find(&self, id: i32) -> Result<Option<UserData>, DbError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.rows.get(&id).map(|row| row.view(id)))
+    }
+
+    async fn find_credentials(&self, username: &str) -> Result<Option<LoginUser>, DbError> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .rows
+            .values()
+            .find(|row| row.username == username)
+            .map(UserRow::credentials))
+    }
+
+    async fn create(&self, user: &UserData, pwd_hash: &str) -> Result<u64, DbError> {
+        let mut users = self.users.lock().unwrap();
+        users.check_unique(None, &user.username, &user.phone, &user.email)?;
+        users.insert(UserRow {
+            username: user.username.clone(),
+            name: user.name.clone(),
+            surname: user.surname.clone(),
+            phone: user.phone.clone(),
+            email: user.email.clone(),
+            pwd: pwd_hash.to_string(),
+            create_date: Utc::now().naive_utc(),
+            created_by: user.created_by.clone(),
+            write_date: None,
+            update_by: None,
+            active: true,
+            is_admin: false,
+        });
+        Ok(1)
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        user: &UserData,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError> {
+        let mut users = self.users.lock().unwrap();
+        if !users.rows.contains_key(&id) {
+            return Ok(0);
+        }
+        users.check_unique(Some(id), &user.username, &user.phone, &user.email)?;
+        let Some(row) = users.rows.get_mut(&id) else {
+            return Ok(0);
+        };
+        row.username = user.username.clone();
+        row.name = user.name.clone();
+        row.surname = user.surname.clone();
+        row.phone = user.phone.clone();
+        row.email = user.email.clone();
+        row.update_by = user.update_by.clone();
+        row.write_date = Some(write_date);
+        Ok(1)
+    }
+
+    async fn update_password(
+        &self,
+        id: i32,
+        pwd_hash: &str,
+        update_by: &str,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError> {
+        let mut users = self.users.lock().unwrap();
+        let Some(row) = users.rows.get_mut(&id) else {
+            return Ok(0);
+        };
+        row.pwd = pwd_hash.to_string();
+        row.update_by = Some(update_by.to_string());
+        row.write_date = Some(write_date);
+        Ok(1)
+    }
+
+    async fn remove(&self, id: i32) -> Result<Option<String>, DbError> {
+        let mut users = self.users.lock().unwrap();
+        Ok(users.rows.remove(&id).map(|row| row.username))
+    }
+
+    async fn remove_many(&self, ids: &[i32]) -> Result<Vec<String>, DbError> {
+        let mut users = self.users.lock().unwrap();
+        Ok(ids
+            .iter()
+            .filter_map(|id| users.rows.remove(id))
+            .map(|row| row.username)
+            .collect())
+    }
+
+    async fn admin_exists(&self) -> Result<bool, DbError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.rows.values().any(|row| row.is_admin))
+    }
+
+    async fn admin_credentials(&self) -> Result<Vec<(String, String)>, DbError> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .rows
+            .values()
+            .filter(|row| row.is_admin)
+            .map(|row| (row.username.clone(), row.pwd.clone()))
+            .collect())
+    }
+
+    async fn create_first_admin(
+        &self,
+        account: &AdminAccount,
+        pwd_hash: &str,
+    ) -> Result<bool, DbError> {
+        let mut users = self.users.lock().unwrap();
+        if users.rows.values().any(|row| row.is_admin) {
+            return Ok(false);
+        }
+        users.check_unique(None, &account.username, &account.phone, &account.email)?;
+        users.insert(UserRow {
+            username: account.username.clone(),
+            name: account.name.clone(),
+            surname: account.surname.clone(),
+            phone: account.phone.clone(),
+            email: account.email.clone(),
+            pwd: pwd_hash.to_string(),
+            create_date: Utc::now().naive_utc(),
+            created_by: account.username.clone(),
+            write_date: None,
+            update_by: None,
+            active: true,
+            is_admin: true,
+        });
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl SessionRepository for MemoryStore {
+    async fn save(
+        &self,
+        username: &str,
+        token_hash: &str,
+        _created_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DbError> {
+        self.sessions.lock().unwrap().insert(
+            username.to_string(),
+            Session {
+                token_hash: token_hash.to_string(),
+                expires_at,
+            },
+        );
+        Ok(())
+    }
+
+    async fn find_active(
+        &self,
+        username: &str,
+        token_hash: &str,
+    ) -> Result<Option<ActiveSession>, DbError> {
+        let expires_at = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(username)
+            .filter(|s| s.token_hash == token_hash && s.expires_at > Utc::now())
+            .map(|s| s.expires_at);
+        let user_active = self
+            .users
+            .lock()
+            .unwrap()
+            .rows
+            .values()
+            .any(|row| row.username == username && row.active);
+        Ok(expires_at
+            .filter(|_| user_active)
+            .map(|expires_at| ActiveSession {
+                username: username.to_string(),
+                expires_at,
+            }))
+    }
+
+    async fn find_user(&self, token_hash: &str) -> Result<Option<ActiveSession>, DbError> {
+        let owner = self
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, s)| s.token_hash == token_hash)
+            .map(|(username, _)| username.clone());
+        match owner {
+            Some(username) => self.find_active(&username, token_hash).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn revoke(&self, usernames: &[String]) -> Result<(), DbError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        for username in usernames {
+            sessions.remove(username);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Readiness for MemoryStore {
+    async fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn check_migrations(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+pub mod memory;
+pub mod postgres;
+mod sql_store;
+pub mod sqlite;
+
+pub use memory::MemoryStore;
+pub use postgres::PgStore;
+pub use sqlite::SqliteStore;
+
+use crate::database::bootstrap::AdminAccount;
+use crate::database::errors::DbError;
+use crate::routes::login::UserData as LoginUser;
+use crate::routes::users::UserData;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// User accounts. Counts returned by the write methods are rows affected,
+/// so `0` means no user matched.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn list(&self) -> Result<Vec<UserData>, DbError>;
+
+    async fn find(&self, id: i32) -> Result<Option<UserData>, DbError>;
+
+    /// The account as `/login` needs it, password hash included.
+    async fn find_credentials(&self, username: &str) -> Result<Option<LoginUser>, DbError>;
+
+    async fn create(&self, user: &UserData, pwd_hash: &str) -> Result<u64, DbError>;
+
+    async fn update(
+        &self,
+        id: i32,
+        user: &UserData,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError>;
+
+    async fn update_password(
+        &self,
+        id: i32,
+        pwd_hash: &str,
+        update_by: &str,
+        write_date: NaiveDateTime,
+    ) -> Result<u64, DbError>;
+
+    /// Returns the username of the removed user, if any.
+    async fn remove(&self, id: i32) -> Result<Option<String>, DbError>;
+
+    /// Returns the usernames of the users that were removed.
+    async fn remove_many(&self, ids: &[i32]) -> Result<Vec<String>, DbError>;
+
+    async fn admin_exists(&self) -> Result<bool, DbError>;
+
+    /// `(username, password hash)` of every admin.
+    async fn admin_credentials(&self) -> Result<Vec<(String, String)>, DbError>;
+
+    /// Inserts `account` as an admin only while no admin exists. Returns
+    /// `false` when another admin got there first.
+    async fn create_first_admin(
+        &self,
+        account: &AdminAccount,
+        pwd_hash: &str,
+    ) -> Result<bool, DbError>;
+}
+
+/// A session that is current and unexpired for an active user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveSession {
+    pub username: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Login sessions, one per user; a new login replaces the previous one.
+/// Sessions are identified by `auth::token::session_hash` of the token,
+/// never the token itself.
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn save(
+        &self,
+        username: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DbError>;
+
+    /// The session if `token_hash` is the user's current one, it has not
+    /// expired and the user is still active.
+    async fn find_active(
+        &self,
+        username: &str,
+        token_hash: &str,
+    ) -> Result<Option<ActiveSession>, DbError>;
+
+    /// The session `token_hash`, under the same conditions as
+    /// `find_active`. Used for tokens that do not name their user.
+    async fn find_user(&self, token_hash: &str) -> Result<Option<ActiveSession>, DbError>;
+
+    /// Ends the sessions of `usernames`, e.g. on logout or password change.
+    async fn revoke(&self, usernames: &[String]) -> Result<(), DbError>;
+}
+
+/// What `/readyz` checks on the storage backend.
+#[async_trait]
+pub trait Readiness: Send + Sync {
+    async fn ping(&self) -> Result<(), String>;
+
+    /// Every migration this binary ships is applied and there are none it
+    /// does not know about.
+    async fn check_migrations(&self) -> Result<(), String>;
+}
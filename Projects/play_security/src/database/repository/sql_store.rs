@@ -0,0 +1,247 @@
+//! The repository impls `PgStore` and `SqliteStore` share. The queries only
+//! differ in their SQL, so each backend module defines its own constants and
+//! invokes `sql_store!`, which expands in that module and picks up:
+//!
+//! - the SQL constants and `DB_SYSTEM`;
+//! - `list_param`, which turns a slice into what the dialect binds for
+//!   `REMOVE_MULTIPLE_USERS` and `REVOKE_SESSIONS`;
+//! - `SessionExpiry`, the type `expire_datetime` decodes to, and
+//!   `active_session`, which turns a session row into an `ActiveSession`;
+//! - the imports the impls below name, including `acquire`.
+
+use crate::telemetry::metrics;
+use sqlx::Pool;
+use sqlx::pool::PoolConnection;
+use std::time::Instant;
+
+/// Checks out a connection for one query, recording how long it waited in
+/// `db_pool_acquire_wait_seconds`.
+pub(super) async fn acquire<DB: sqlx::Database>(
+    pool: &Pool<DB>,
+) -> Result<PoolConnection<DB>, sqlx::Error> {
+    let started = Instant::now();
+    let conn = pool.acquire().await;
+    metrics::record_pool_wait(started);
+    conn
+}
+
+/// Implements `UserRepository`, `SessionRepository` and `Readiness` for a
+/// store with a `pool` field.
+macro_rules! sql_store {
+    ($store:ident) => {
+        #[async_trait]
+        impl UserRepository for $store {
+            async fn list(&self) -> Result<Vec<UserData>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_as::<_, UserData>(GET_ALL_USERS)
+                    .fetch_all(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, GET_ALL_USERS))
+                    .await?)
+            }
+
+            async fn find(&self, id: i32) -> Result<Option<UserData>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_as::<_, UserData>(FETCH_SINGLE_USER)
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, FETCH_SINGLE_USER))
+                    .await?)
+            }
+
+            async fn find_credentials(&self, username: &str) -> Result<Option<LoginUser>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_as::<_, LoginUser>(FETCH_USER_DATA)
+                    .bind(username)
+                    .fetch_optional(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, FETCH_USER_DATA))
+                    .await?)
+            }
+
+            async fn create(&self, user: &UserData, pwd_hash: &str) -> Result<u64, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                let result = sqlx::query(CREATE_USER)
+                    .bind(&user.username)
+                    .bind(&user.name)
+                    .bind(&user.surname)
+                    .bind(&user.phone)
+                    .bind(&user.email)
+                    .bind(pwd_hash)
+                    .bind(&user.created_by)
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, CREATE_USER))
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn update(
+                &self,
+                id: i32,
+                user: &UserData,
+                write_date: NaiveDateTime,
+            ) -> Result<u64, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                let result = sqlx::query(UPDATE_USER)
+                    .bind(&user.username)
+                    .bind(&user.name)
+                    .bind(&user.surname)
+                    .bind(&user.phone)
+                    .bind(&user.email)
+                    .bind(&user.update_by)
+                    .bind(write_date)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, UPDATE_USER))
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn update_password(
+                &self,
+                id: i32,
+                pwd_hash: &str,
+                update_by: &str,
+                write_date: NaiveDateTime,
+            ) -> Result<u64, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                let result = sqlx::query(UPDATE_USER_PWD)
+                    .bind(pwd_hash)
+                    .bind(update_by)
+                    .bind(write_date)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, UPDATE_USER_PWD))
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn remove(&self, id: i32) -> Result<Option<String>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_scalar(REMOVE_USER)
+                    .bind(id)
+                    .fetch_optional(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, REMOVE_USER))
+                    .await?)
+            }
+
+            async fn remove_many(&self, ids: &[i32]) -> Result<Vec<String>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_scalar(REMOVE_MULTIPLE_USERS)
+                    .bind(list_param(ids))
+                    .fetch_all(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, REMOVE_MULTIPLE_USERS))
+                    .await?)
+            }
+
+            async fn admin_exists(&self) -> Result<bool, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_scalar(ADMIN_EXISTS)
+                    .fetch_one(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, ADMIN_EXISTS))
+                    .await?)
+            }
+
+            async fn admin_credentials(&self) -> Result<Vec<(String, String)>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                Ok(sqlx::query_as(FETCH_ADMINS)
+                    .fetch_all(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, FETCH_ADMINS))
+                    .await?)
+            }
+
+            async fn create_first_admin(
+                &self,
+                account: &AdminAccount,
+                pwd_hash: &str,
+            ) -> Result<bool, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                let result = sqlx::query(INSERT_FIRST_ADMIN)
+                    .bind(&account.username)
+                    .bind(&account.name)
+                    .bind(&account.surname)
+                    .bind(&account.phone)
+                    .bind(&account.email)
+                    .bind(pwd_hash)
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, INSERT_FIRST_ADMIN))
+                    .await?;
+                Ok(result.rows_affected() == 1)
+            }
+        }
+
+        #[async_trait]
+        impl SessionRepository for $store {
+            async fn save(
+                &self,
+                username: &str,
+                token_hash: &str,
+                created_at: DateTime<Utc>,
+                expires_at: DateTime<Utc>,
+            ) -> Result<(), DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                sqlx::query(UPSERT_USER_LOGIN)
+                    .bind(username)
+                    .bind(token_hash)
+                    .bind(created_at)
+                    .bind(expires_at)
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, UPSERT_USER_LOGIN))
+                    .await?;
+                Ok(())
+            }
+
+            async fn find_active(
+                &self,
+                username: &str,
+                token_hash: &str,
+            ) -> Result<Option<ActiveSession>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                let found: Option<(String, SessionExpiry)> = sqlx::query_as(FETCH_SESSION)
+                    .bind(username)
+                    .bind(token_hash)
+                    .fetch_optional(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, FETCH_SESSION))
+                    .await?;
+                Ok(found.map(active_session))
+            }
+
+            async fn find_user(&self, token_hash: &str) -> Result<Option<ActiveSession>, DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                let found: Option<(String, SessionExpiry)> = sqlx::query_as(FETCH_SESSION_USER)
+                    .bind(token_hash)
+                    .fetch_optional(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, FETCH_SESSION_USER))
+                    .await?;
+                Ok(found.map(active_session))
+            }
+
+            async fn revoke(&self, usernames: &[String]) -> Result<(), DbError> {
+                let mut conn = acquire(&self.pool).await?;
+                sqlx::query(REVOKE_SESSIONS)
+                    .bind(list_param(usernames))
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, REVOKE_SESSIONS))
+                    .await?;
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl Readiness for $store {
+            async fn ping(&self) -> Result<(), String> {
+                let mut conn = acquire(&self.pool).await.map_err(|e| e.to_string())?;
+                sqlx::query(PING)
+                    .execute(&mut *conn)
+                    .instrument(query_span(DB_SYSTEM, PING))
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+
+            async fn check_migrations(&self) -> Result<(), String> {
+                migrations::check(&self.pool).await
+            }
+        }
+    };
+}
+
+pub(super) use sql_store;
@@ -0,0 +1,173 @@
+use super::sql_store::{acquire, sql_store};
+use super::{ActiveSession, Readiness, SessionRepository, UserRepository};
+use crate::database::bootstrap::AdminAccount;
+use crate::database::errors::DbError;
+use crate::database::migrations;
+use crate::routes::login::UserData as LoginUser;
+use crate::routes::users::UserData;
+use crate::telemetry::otel::query_span;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::SqlitePool;
+use tracing::Instrument;
+
+const DB_SYSTEM: &str = "sqlite";
+
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    pub fn new(pool: SqlitePool) -> SqliteStore {
+        SqliteStore { pool }
+    }
+}
+
+sql_store!(SqliteStore);
+
+/// SQLite has no array binds, so lists go in as a JSON array and are
+/// unpacked with `json_each`.
+fn list_param<T: serde::Serialize>(values: &[T]) -> String {
+    serde_json::to_string(values).unwrap_or_else(|_| "[]".to_string())
+}
+
+type SessionExpiry = DateTime<Utc>;
+
+fn active_session((username, expires_at): (String, SessionExpiry)) -> ActiveSession {
+    ActiveSession {
+        username,
+        expires_at,
+    }
+}
+
+const PING: &str = "SELECT 1";
+
+const GET_ALL_USERS: &str = "
+SELECT id
+    ,username
+    ,name
+    ,surname
+    ,phone
+    ,email
+    ,create_date
+    ,created_by
+    ,write_date
+    ,update_by
+FROM users
+ORDER BY id
+";
+
+const FETCH_SINGLE_USER: &str = "
+SELECT id
+    ,username
+    ,name
+    ,surname
+    ,phone
+    ,email
+    ,create_date
+    ,created_by
+    ,write_date
+    ,update_by
+FROM users
+WHERE id = ?1
+";
+
+const FETCH_USER_DATA: &str = "
+SELECT username
+    ,name
+    ,surname
+    ,phone
+    ,email
+    ,pwd
+    ,active
+FROM users
+WHERE username = ?1
+";
+
+const REMOVE_USER: &str = "
+DELETE FROM users
+WHERE id = ?1
+RETURNING username
+";
+
+const REMOVE_MULTIPLE_USERS: &str = "
+DELETE FROM users
+WHERE id IN (SELECT value FROM json_each(?1))
+RETURNING username
+";
+
+const CREATE_USER: &str = "
+INSERT INTO users (username, name, surname, phone, email, pwd, created_by)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)
+";
+
+const UPDATE_USER: &str = "
+UPDATE users
+SET username = ?1
+    ,name = ?2
+    ,surname = ?3
+    ,phone = ?4
+    ,email = ?5
+    ,update_by = ?6
+    ,write_date = ?7
+WHERE id = ?8
+";
+
+const UPDATE_USER_PWD: &str = "
+UPDATE users
+SET pwd = ?1
+    ,update_by = ?2
+    ,write_date = ?3
+WHERE id = ?4
+";
+
+const ADMIN_EXISTS: &str = "
+SELECT EXISTS(SELECT 1 FROM users WHERE is_admin)
+";
+
+const FETCH_ADMINS: &str = "
+SELECT username
+    ,pwd
+FROM users
+WHERE is_admin
+";
+
+const INSERT_FIRST_ADMIN: &str = "
+INSERT INTO users (username, name, surname, phone, email, pwd, created_by, is_admin)
+SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?1, TRUE
+WHERE NOT EXISTS (SELECT 1 FROM users WHERE is_admin)
+";
+
+const UPSERT_USER_LOGIN: &str = "
+INSERT INTO user_login (username, token_hash, created_datetime, expire_datetime)
+VALUES (?1, ?2, ?3, ?4)
+ON CONFLICT (username)
+DO UPDATE SET
+    token_hash = excluded.token_hash,
+    created_datetime = excluded.created_datetime,
+    expire_datetime = excluded.expire_datetime
+";
+
+// Timestamps are stored as RFC 3339 text; julianday() parses the offset so
+// the comparison does not depend on how many fractional digits were written.
+const FETCH_SESSION: &str = "
+SELECT l.username, l.expire_datetime FROM user_login l
+JOIN users u ON u.username = l.username
+WHERE l.username = ?1 AND l.token_hash = ?2
+    AND julianday(l.expire_datetime) > julianday('now')
+    AND COALESCE(u.active, TRUE)
+";
+
+const FETCH_SESSION_USER: &str = "
+SELECT l.username, l.expire_datetime FROM user_login l
+JOIN users u ON u.username = l.username
+WHERE l.token_hash = ?1
+    AND julianday(l.expire_datetime) > julianday('now')
+    AND COALESCE(u.active, TRUE)
+";
+
+const REVOKE_SESSIONS: &str = "
+DELETE FROM user_login
+WHERE username IN (SELECT value FROM json_each(?1))
+";
@@ -0,0 +1,8 @@
+use crate::response::responses::Response;
+use axum::extract::FromRequestParts;
+
+/// `axum::extract::Path` that rejects with a problem+json body instead of
+/// plain text.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(Response))]
+pub struct Path<T>(pub T);
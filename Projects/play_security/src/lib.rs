@@ -0,0 +1,49 @@
+pub mod auth;
+pub mod config;
+pub mod database;
+pub mod extract;
+pub mod middleware;
+pub mod response;
+pub mod routes;
+pub mod shutdown;
+pub mod state;
+pub mod telemetry;
+pub mod tls;
+pub mod validation;
+
+use axum::Router;
+use database::bootstrap::SetupToken;
+use metrics_exporter_prometheus::PrometheusHandle;
+use state::AppState;
+
+/// The full HTTP API. `setup` mounts `POST /setup` while no admin exists;
+/// `metrics` mounts `GET /metrics` when it is not served on its own port.
+pub fn app(
+    state: AppState,
+    setup: Option<SetupToken>,
+    metrics: Option<PrometheusHandle>,
+) -> Router {
+    let config = state.config.clone();
+    let app = routes::home::home_route()
+        .merge(routes::health::health_route(state.clone()))
+        .merge(routes::openapi::openapi_route())
+        .merge(routes::users::users_route(state.clone()))
+        .merge(routes::login::login_route(state.clone()));
+    let app = match setup {
+        Some(token) => app.merge(routes::setup::setup_route(state.users, token)),
+        None => app,
+    };
+    let app = match metrics {
+        Some(handle) => app.merge(telemetry::metrics::metrics_route(handle)),
+        None => app,
+    };
+    app.fallback(routes::error::error_route)
+        .layer(axum::middleware::from_fn(telemetry::metrics::track_http))
+        .layer(axum::middleware::from_fn(
+            middleware::problem::problem_instance,
+        ))
+        .layer(axum::middleware::from_fn(
+            middleware::request_id::request_id,
+        ))
+        .layer(middleware::cors::cors_layer(config))
+}
@@ -1,52 +1,298 @@
-mod auth;
-mod database;
-mod middleware;
-mod response;
-mod routes;
-
-use sqlx::PgPool;
-use std::net::SocketAddr;
+use axum::Router;
+use play_security::auth::rate_limit::LoginRateLimiter;
+use play_security::auth::session_cache::{self, SessionCache};
+use play_security::config::{self, Config, LogFormat, SharedConfig};
+use play_security::database::bootstrap::BootstrapOutcome;
+use play_security::database::migrations::Migrations;
+use play_security::database::{self, Database};
+use play_security::shutdown::{self, Drained, Shutdown};
+use play_security::state::AppState;
+use play_security::{telemetry, tls};
+use sqlx::Pool;
+use std::future::IntoFuture;
+use std::process::ExitCode;
+use std::sync::Arc;
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{error, info, warn};
+use tracing_subscriber::{
+    EnvFilter, Layer, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
 
 #[tokio::main]
-async fn main() {
-    dotenv::dotenv().expect("Failed to load .env file");
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .compact()
+async fn main() -> ExitCode {
+    let loaded = Config::load();
+    let log_config = loaded.as_ref().map(|c| c.log.clone()).unwrap_or_default();
+    let (log_filter, log_level_handle) = reload::Layer::new(EnvFilter::new(&log_config.level));
+    let log_output = match log_config.format {
+        LogFormat::Text => fmt::layer().with_target(false).compact().boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let tracing_config = loaded
+        .as_ref()
+        .map(|c| c.tracing.clone())
+        .unwrap_or_default();
+    let tracer_provider = telemetry::otel::init(&tracing_config);
+    let trace_export = tracer_provider
+        .as_ref()
+        .ok()
+        .and_then(Option::as_ref)
+        .map(telemetry::otel::layer);
+    // `log.level` filters the local logs only; trace export filters itself.
+    tracing_subscriber::registry()
+        .with(log_output.with_filter(log_filter))
+        .with(trace_export)
         .init();
 
     info!("Starting server...");
+    let config = match loaded {
+        Ok(c) => c,
+        Err(e) => {
+            error!("CONFIG: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    telemetry::redact::set_policy(config.log.pii);
+    info!("CONFIG: {:?}", config);
+    let tracer_provider = match tracer_provider {
+        Ok(provider) => provider,
+        Err(e) => {
+            error!("TRACING: Failed to start trace exporter: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
     info!("Initializing Database");
     //Database
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-
-    let pool: PgPool = database::create_pool(&database_url)
-        .await
-        .expect("Failed to connect to database");
+    let database = match Database::connect(&config).await {
+        Ok(database) => database,
+        Err(e) => {
+            error!("DATABASE: Failed to connect: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
 
     info!("Database connection established");
-    //tables
-    database::models::Tables::initialize_tables(&pool)
-        .await
-        .expect("Failed to initialize tables");
 
-    info!("Tables initialized successfully");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        let result = match &database {
+            Database::Postgres(pool) => migrate_command(pool, &args[1..]).await,
+            Database::Sqlite(pool) => migrate_command(pool, &args[1..]).await,
+            Database::Memory(_) => {
+                Err("The migrate command needs a Postgres or SQLite database.url".to_string())
+            }
+        };
+        database.close().await;
+        if let Err(e) = result {
+            error!("MIGRATIONS: {}", e);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    //Migrations
+    if let Err(e) = database.migrate().await {
+        error!("MIGRATIONS: Failed to apply: {}", e);
+        return ExitCode::FAILURE;
+    }
+    info!("Migrations applied successfully");
+
+    //Admin bootstrap
+    let bootstrap = match database::bootstrap::bootstrap_admin(
+        database.users().as_ref(),
+        config.admin.account(),
+        config.is_production(),
+    )
+    .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            error!("BOOTSTRAP: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let shutdown = Shutdown::default();
+    shutdown.listen_for_signals();
+
+    //Metrics
+    let metrics = match config.metrics.enabled {
+        true => match telemetry::metrics::install() {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                error!("METRICS: Failed to install recorder: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        false => None,
+    };
+    if let Some(handle) = &metrics {
+        telemetry::metrics::spawn_upkeep(handle.clone(), database.clone(), &shutdown);
+        if let Some(admin_addr) = config.metrics.admin_addr {
+            let listener = match TcpListener::bind(admin_addr).await {
+                Ok(l) => l,
+                Err(e) => {
+                    error!("METRICS: Failed to bind {}: {}", admin_addr, e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            info!("Metrics available on http://{}/metrics", admin_addr);
+            let admin = telemetry::metrics::metrics_route(handle.clone());
+            shutdown.spawn(async move {
+                if let Err(e) = axum::serve(listener, admin.into_make_service()).await {
+                    error!("METRICS: Admin listener stopped: {}", e);
+                }
+            });
+        }
+    }
+
+    //Session cache
+    let mut sessions = database.sessions();
+    if config.session_cache.ttl_secs > 0 {
+        let cache = Arc::new(SessionCache::new(sessions, &config.session_cache));
+        if let Database::Postgres(pool) = &database {
+            session_cache::spawn_invalidation_listener(pool.clone(), cache.clone(), &shutdown);
+        }
+        sessions = cache;
+    }
 
     //Routes
-    let app = routes::home::home_route()
-        .merge(routes::users::users_route(pool.clone()))
-        .merge(routes::login::login_route(pool.clone()))
-        .fallback(routes::error::error_route);
+    let config = SharedConfig::new(config);
+    config::reload::spawn_reloader(config.clone(), log_level_handle, &shutdown);
+
+    let state = AppState {
+        users: database.users(),
+        sessions,
+        readiness: database.readiness(),
+        config: config.clone(),
+        login_limiter: Arc::new(LoginRateLimiter::default()),
+    };
+    let setup = match bootstrap {
+        BootstrapOutcome::AwaitingSetup(token) => Some(token),
+        _ => None,
+    };
+    let metrics = metrics.filter(|_| config.load().metrics.admin_addr.is_none());
+    let app = play_security::app(state, setup, metrics);
     info!("Routes initialized successfully");
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    let listener = TcpListener::bind(addr).await.unwrap();
-    info!("Server listening on port {}", addr.port());
-    info!("Server running on http://{}", addr);
+    let served = serve(app, &config.load(), &shutdown).await;
+
+    let deadline = config.load().shutdown_timeout();
+    let finished = shutdown.finish(database.close(), deadline).await;
+    if let Drained::Clean = finished {
+        info!("SHUTDOWN: Background tasks stopped and database pool closed");
+    }
+    if let Some(provider) = tracer_provider {
+        // Flushes pending spans; the OTLP exporter blocks on HTTP.
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("TRACING: Failed to flush spans: {}", e),
+            Err(e) => warn!("TRACING: Failed to flush spans: {}", e),
+        }
+    }
 
-    axum::serve(listener, app.into_make_service())
+    match served {
+        Ok(Drained::Clean) if matches!(finished, Drained::Clean) => {
+            info!("SHUTDOWN: Complete");
+            ExitCode::SUCCESS
+        }
+        Ok(_) => ExitCode::from(shutdown::EXIT_DRAIN_TIMEOUT),
+        Err(e) => {
+            error!("SERVER: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Serves `app` over HTTP or HTTPS until shutdown is triggered and open
+/// requests have drained.
+async fn serve(app: Router, config: &Config, shutdown: &Shutdown) -> Result<Drained, String> {
+    let addr = config.server.bind_addr;
+    let tls_config = &config.tls;
+    let deadline = config.shutdown_timeout();
+
+    if !tls_config.enabled {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("Failed to bind {addr}: {e}"))?;
+        info!("Server listening on port {}", addr.port());
+        info!("Server running on http://{}", addr);
+
+        let server = axum::serve(listener, app.into_make_service())
+            .with_graceful_shutdown(shutdown.triggered());
+        return shutdown
+            .drain(server.into_future(), deadline)
+            .await
+            .map_err(|e| e.to_string());
+    }
+
+    let rustls = tls::rustls_config(tls_config)
+        .map_err(|e| format!("Failed to load TLS certificate: {e}"))?;
+    tls::spawn_cert_reloader(rustls.clone(), tls_config.clone(), shutdown);
+
+    if let Some(redirect_addr) = tls_config.redirect_http_addr {
+        let listener = TcpListener::bind(redirect_addr)
+            .await
+            .map_err(|e| format!("Failed to bind {redirect_addr}: {e}"))?;
+        info!("Redirecting http://{} to HTTPS", redirect_addr);
+        shutdown.spawn(async move {
+            let redirect = tls::redirect::redirect_route(addr.port());
+            if let Err(e) = axum::serve(listener, redirect.into_make_service()).await {
+                error!("TLS: Redirect listener stopped: {}", e);
+            }
+        });
+    }
+
+    let handle = axum_server::Handle::new();
+    let triggered = shutdown.triggered();
+    let graceful = handle.clone();
+    tokio::spawn(async move {
+        triggered.await;
+        graceful.graceful_shutdown(None);
+    });
+
+    info!("Server listening on port {}", addr.port());
+    info!("Server running on https://{}", addr);
+    let server = axum_server::bind(addr)
+        .acceptor(tls::client_cert::ClientCertAcceptor::new(rustls))
+        .handle(handle)
+        .serve(app.into_make_service());
+    shutdown
+        .drain(server, deadline)
         .await
-        .unwrap();
+        .map_err(|e| e.to_string())
+}
+
+/// `play_security migrate [up | down <version> | status]`
+async fn migrate_command<DB: Migrations>(pool: &Pool<DB>, args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        None | Some("up") => database::migrations::run(pool)
+            .await
+            .map_err(|e| e.to_string()),
+        Some("down") => {
+            let target: i64 = args
+                .get(1)
+                .and_then(|v| v.parse().ok())
+                .ok_or("usage: migrate down <version>, use 0 to revert everything")?;
+            database::migrations::rollback(pool, target)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        Some("status") => {
+            let migrations = database::migrations::status(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            for m in migrations {
+                let state = if m.applied { "applied" } else { "pending" };
+                info!("{} {} [{}]", m.version, m.description, state);
+            }
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown migrate command '{other}'")),
+    }
 }
@@ -1,65 +1,128 @@
-use crate::auth::token::verify_jwt;
+use crate::auth::principal::{Permission, ServicePrincipal};
+use crate::auth::token::session_hash;
+use crate::database::repository::ActiveSession;
+use crate::response::responses::Response;
+use crate::state::AppState;
+use crate::telemetry::metrics::record_token_validation;
+use crate::tls::client_cert::ClientCertificate;
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{Method, Request},
     middleware::Next,
-    response::Response,
+    response::Response as AxumResponse,
 };
-use sqlx::PgPool;
 
+/// Callers authenticate with a bearer token, which puts the username in the
+/// request extensions, or with a trusted TLS client certificate, which puts
+/// the matching `ServicePrincipal` there instead. A bearer token wins when
+/// both are present.
 pub async fn auth_middleware(
-    State(pool): State<PgPool>,
+    State(state): State<AppState>,
     mut req: Request<Body>,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<AxumResponse, Response> {
     tracing::info!("AUTH middleware entered");
+    let cert_subject = req
+        .extensions()
+        .get::<ClientCertificate>()
+        .and_then(|c| c.subject.clone());
+    if let Some(subject) = cert_subject
+        && !req.headers().contains_key("Authorization")
+    {
+        let principal = service_principal(&state, &subject, req.method())?;
+        tracing::info!("Client certificate valid for service: {}", principal.name);
+        record_token_validation("accepted", "client_certificate");
+        tracing::Span::current().record(
+            "user",
+            tracing::field::display(format_args!("service:{}", principal.name)),
+        );
+        req.extensions_mut().insert(principal);
+        return Ok(next.run(req).await);
+    }
+
     let auth_header = req
         .headers()
         .get("Authorization")
         .and_then(|h| h.to_str().ok())
         .ok_or_else(|| {
             tracing::error!("Missing Authorization header");
-            StatusCode::UNAUTHORIZED
+            record_token_validation("rejected", "missing_header");
+            Response::Unauthorized.with_detail("Missing Authorization header")
         })?;
 
     tracing::info!("Authorization header OK");
     let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
         tracing::error!("Missing Bearer prefix");
-        StatusCode::UNAUTHORIZED
+        record_token_validation("rejected", "wrong_scheme");
+        Response::Unauthorized.with_detail("Authorization header must use the Bearer scheme")
     })?;
 
     tracing::info!("Bearer token extracted");
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "STAR_WARS_ROCKS".to_string());
-
-    let claims = verify_jwt(token, &secret).await.map_err(|e| {
-        tracing::error!("JWT verification failed: {:?}", e);
-        StatusCode::UNAUTHORIZED
-    })?;
-
-    tracing::info!("JWT valid for user: {}", claims.user);
+    let config = state.config.load();
+    let claims = config
+        .auth
+        .token_issuer()
+        .verify(token, &config.auth)
+        .map_err(|e| {
+            tracing::error!("Token verification failed: {:?}", e);
+            record_token_validation("rejected", "invalid_token");
+            e
+        })?;
 
-    let token_valid: Option<(String,)> = sqlx::query_as(
-        "SELECT token FROM user_login
-         WHERE username = $1 AND token = $2 AND expire_datetime > NOW()",
-    )
-    .bind(&claims.user)
-    .bind(token)
-    .fetch_optional(&pool)
-    .await
+    let token_hash = session_hash(token, config.auth.session_hash_key.expose());
+    let session_user = match claims {
+        Some(claims) => {
+            tracing::info!("Token valid for user: {}", claims.sub);
+            state.sessions.find_active(&claims.sub, &token_hash).await
+        }
+        None => state.sessions.find_user(&token_hash).await,
+    }
     .map_err(|e| {
         tracing::error!("DB error: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        record_token_validation("rejected", "session_lookup_failed");
+        Response::InternalError
     })?;
 
-    if token_valid.is_none() {
+    let Some(ActiveSession { username: user, .. }) = session_user else {
         tracing::error!("Token not found or expired in DB");
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+        record_token_validation("rejected", "session_not_found");
+        return Err(Response::InvalidToken(
+            "Session not found or expired".to_string(),
+        ));
+    };
 
     tracing::info!("Token validated against DB");
+    record_token_validation("accepted", "bearer");
 
-    req.extensions_mut().insert(claims.user);
+    tracing::Span::current().record("user", user.as_str());
+    req.extensions_mut().insert(user);
 
     Ok(next.run(req).await)
 }
+
+/// Maps a verified client certificate to its configured service and checks
+/// the service may make this kind of request.
+fn service_principal(
+    state: &AppState,
+    subject: &str,
+    method: &Method,
+) -> Result<ServicePrincipal, Response> {
+    let config = state.config.load();
+    let principal = config.service_principal(subject).ok_or_else(|| {
+        tracing::error!("No service principal for client certificate: {}", subject);
+        record_token_validation("rejected", "unmapped_certificate");
+        Response::Unauthorized.with_detail("Client certificate is not mapped to a service")
+    })?;
+
+    let required = Permission::required_for(method);
+    if !principal.allows(required) {
+        tracing::error!("Service {} lacks {:?}", principal.name, required);
+        record_token_validation("rejected", "missing_permission");
+        return Err(Response::Forbidden.with_detail(format!(
+            "Service '{}' is not allowed to do this",
+            principal.name
+        )));
+    }
+    Ok(principal.clone())
+}
@@ -1 +1,4 @@
 pub mod auth;
+pub mod cors;
+pub mod problem;
+pub mod request_id;
@@ -0,0 +1,32 @@
+use crate::middleware::request_id::RequestId;
+use crate::response::problem::Problem;
+use axum::{
+    body::Body,
+    http::{Request, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Fills in the `instance` member of problem+json bodies with the request
+/// path, and adds a `request_id` member, neither of which `IntoResponse` has
+/// access to.
+pub async fn problem_instance(req: Request<Body>, next: Next) -> Response {
+    let path = req.uri().path().to_owned();
+    let request_id = req.extensions().get::<RequestId>().cloned();
+    let res = next.run(req).await;
+
+    let mut problem = match res.extensions().get::<Problem>() {
+        Some(p) if p.instance.is_none() => p.clone().with_instance(path),
+        _ => return res,
+    };
+    if let Some(RequestId(id)) = request_id {
+        problem = problem.with_extension("request_id", id);
+    }
+
+    let (mut parts, _) = res.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let rebuilt = problem.into_response();
+    let (new_parts, body) = rebuilt.into_parts();
+    parts.extensions = new_parts.extensions;
+    Response::from_parts(parts, body)
+}
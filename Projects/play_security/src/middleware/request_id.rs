@@ -0,0 +1,82 @@
+use crate::telemetry::otel;
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use rand::RngCore;
+use std::time::Instant;
+use tracing::{Instrument, field, info, info_span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Correlation id of the current request, available from request extensions.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Reuses the caller's `X-Request-Id` when it looks sane, otherwise makes a
+/// new one. Every log line of the request is emitted inside a span carrying
+/// the id, and the id is echoed in the response header. The span is also the
+/// exported server span, continuing the caller's `traceparent` if sent.
+pub async fn request_id(mut req: Request<Body>, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_valid(v))
+        .map(str::to_string)
+        .unwrap_or_else(generate);
+
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| format!(" {}", p.as_str()))
+        .unwrap_or_default();
+    let span = info_span!(
+        "request",
+        otel.name = %format_args!("{}{route}", req.method()),
+        otel.kind = "server",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.uri().path(),
+        user = field::Empty,
+        http.response.status_code = field::Empty,
+    );
+    span.set_parent(otel::remote_context(req.headers()));
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    async move {
+        let started = Instant::now();
+        let mut res = next.run(req).await;
+        tracing::Span::current().record("http.response.status_code", res.status().as_u16());
+        info!(
+            status = res.status().as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            "REQUEST: Completed"
+        );
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            res.headers_mut().insert(X_REQUEST_ID.clone(), value);
+        }
+        res
+    }
+    .instrument(span)
+    .await
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+fn generate() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
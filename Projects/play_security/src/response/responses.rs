@@ -1,7 +1,12 @@
-use axum::http::StatusCode;
+use axum::extract::rejection::{JsonRejection, PathRejection};
+use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response as AxumResponse};
 use jsonwebtoken::errors::Error as JwtError;
 
+use crate::database::errors::DbError;
+use crate::response::problem::Problem;
+use crate::validation::FieldError;
+
 #[derive(Debug, Clone)]
 pub enum Response {
     Success,
@@ -10,12 +15,18 @@ pub enum Response {
     InternalError,
     BadRequest,
     Unauthorized,
-    // Forbidden,
+    InvalidCredentials,
+    Forbidden,
     InvalidToken(String),
+    ValidationFailed(Vec<FieldError>),
+    Conflict(String),
+    ConstraintViolation(String),
+    TooManyRequests(u64),
+    Problem(Box<Problem>),
 }
 
 impl Response {
-    pub const fn status_code(&self) -> StatusCode {
+    pub fn status_code(&self) -> StatusCode {
         match self {
             Response::Success => StatusCode::OK,
             Response::NotFound => StatusCode::NOT_FOUND,
@@ -23,8 +34,34 @@ impl Response {
             Response::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
             Response::BadRequest => StatusCode::BAD_REQUEST,
             Response::Unauthorized => StatusCode::UNAUTHORIZED,
-            // Response::Forbidden => StatusCode::FORBIDDEN,
+            Response::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            Response::Forbidden => StatusCode::FORBIDDEN,
             Response::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Response::ValidationFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Response::Conflict(_) => StatusCode::CONFLICT,
+            Response::ConstraintViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Response::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Response::Problem(p) => p.status_code(),
+        }
+    }
+
+    /// Stable machine readable error code, also used for the problem type URI.
+    pub fn code(&self) -> String {
+        match self {
+            Response::Success => "success".to_string(),
+            Response::NotFound => "not_found".to_string(),
+            Response::NoUserFound => "user_not_found".to_string(),
+            Response::InternalError => "internal_error".to_string(),
+            Response::BadRequest => "bad_request".to_string(),
+            Response::Unauthorized => "unauthorized".to_string(),
+            Response::InvalidCredentials => "invalid_credentials".to_string(),
+            Response::Forbidden => "forbidden".to_string(),
+            Response::InvalidToken(_) => "invalid_token".to_string(),
+            Response::ValidationFailed(_) => "validation_failed".to_string(),
+            Response::Conflict(_) => "duplicate_value".to_string(),
+            Response::ConstraintViolation(_) => "constraint_violation".to_string(),
+            Response::TooManyRequests(_) => "rate_limited".to_string(),
+            Response::Problem(p) => p.code.clone(),
         }
     }
 
@@ -36,20 +73,50 @@ impl Response {
             Response::InternalError => "Internal Server Error".to_string(),
             Response::BadRequest => "Bad Request".to_string(),
             Response::Unauthorized => "Unauthorized".to_string(),
-            // Response::Forbidden => "Forbidden",
-            Response::InvalidToken(msg) => format!("Token Invalid: {}", msg),
+            Response::InvalidCredentials => "Invalid Credentials".to_string(),
+            Response::Forbidden => "Forbidden".to_string(),
+            Response::InvalidToken(_) => "Token Invalid".to_string(),
+            Response::ValidationFailed(_) => "Validation Failed".to_string(),
+            Response::Conflict(_) => "Conflict".to_string(),
+            Response::ConstraintViolation(_) => "Constraint Violation".to_string(),
+            Response::TooManyRequests(_) => "Too Many Requests".to_string(),
+            Response::Problem(p) => p.title.clone(),
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let problem = Problem::new(self.status_code(), &self.code(), &self.message());
+        match self {
+            Response::Problem(p) => (**p).clone(),
+            Response::InvalidToken(msg) => problem.with_detail(msg.clone()),
+            Response::InvalidCredentials => problem.with_detail("Invalid username or password"),
+            Response::ValidationFailed(errors) => problem
+                .with_detail("One or more fields failed validation")
+                .with_extension("errors", errors),
+            Response::Conflict(field) => problem
+                .with_detail(format!("A user with this {field} already exists"))
+                .with_extension("field", field),
+            Response::ConstraintViolation(msg) => problem.with_detail(msg.clone()),
+            Response::TooManyRequests(secs) => problem
+                .with_detail(format!("Too many attempts, retry in {secs} seconds"))
+                .with_extension("retry_after_secs", secs),
+            _ => problem,
         }
     }
+
+    pub fn with_detail(self, detail: impl Into<String>) -> Response {
+        Response::Problem(Box::new(self.problem().with_detail(detail)))
+    }
 }
 
 impl IntoResponse for Response {
     fn into_response(self) -> AxumResponse {
-        let body = serde_json::json!({
-            "code": self.status_code().as_u16(),
-            "message": self.message()
-        });
-
-        (self.status_code(), axum::Json(body)).into_response()
+        let mut res = self.problem().into_response();
+        if let Response::TooManyRequests(secs) = self {
+            res.headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        res
     }
 }
 
@@ -58,3 +125,42 @@ impl From<JwtError> for Response {
         Response::InvalidToken(err.to_string())
     }
 }
+
+impl From<DbError> for Response {
+    fn from(err: DbError) -> Self {
+        match err {
+            DbError::UniqueViolation { field } => Response::Conflict(field),
+            DbError::ForeignKeyViolation { constraint } => Response::ConstraintViolation(format!(
+                "The request references a record that does not exist ({constraint})"
+            )),
+            DbError::CheckViolation { constraint } => {
+                Response::ConstraintViolation(format!("The request violates the {constraint} rule"))
+            }
+            DbError::NotNullViolation { field } => {
+                Response::ConstraintViolation(format!("{field} is required"))
+            }
+            DbError::ValueTooLong => {
+                Response::ConstraintViolation("A value is longer than its field allows".to_string())
+            }
+            DbError::Unexpected(_) => Response::InternalError,
+        }
+    }
+}
+
+impl From<JsonRejection> for Response {
+    fn from(err: JsonRejection) -> Self {
+        Response::Problem(Box::new(
+            Problem::new(err.status(), "malformed_body", "Malformed Request Body")
+                .with_detail(err.body_text()),
+        ))
+    }
+}
+
+impl From<PathRejection> for Response {
+    fn from(err: PathRejection) -> Self {
+        Response::Problem(Box::new(
+            Problem::new(err.status(), "invalid_path", "Invalid Path Parameter")
+                .with_detail(err.body_text()),
+        ))
+    }
+}
@@ -0,0 +1,140 @@
+use crate::auth::token;
+use crate::state::AppState;
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// Upper bound for each database check, so a hung connection reports the
+/// instance as not ready instead of hanging the probe.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    pub status: &'static str,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    pub status: &'static str,
+    pub checks: ReadinessChecks,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessChecks {
+    pub database: ComponentCheck,
+    pub migrations: ComponentCheck,
+    pub signing_keys: ComponentCheck,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ComponentCheck {
+    pub status: &'static str,
+    pub latency_ms: u128,
+    /// A fixed description; the underlying error is only logged, since the
+    /// endpoint is unauthenticated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<&'static str>,
+}
+
+impl ComponentCheck {
+    fn from_result(
+        started: Instant,
+        result: Result<(), String>,
+        component: &str,
+        detail: &'static str,
+    ) -> ComponentCheck {
+        let latency_ms = started.elapsed().as_millis();
+        match result {
+            Ok(()) => ComponentCheck {
+                status: "up",
+                latency_ms,
+                detail: None,
+            },
+            Err(e) => {
+                warn!("HEALTH: {} check failed: {}", component, e);
+                ComponentCheck {
+                    status: "down",
+                    latency_ms,
+                    detail: Some(detail),
+                }
+            }
+        }
+    }
+
+    fn is_up(&self) -> bool {
+        self.status == "up"
+    }
+}
+
+pub fn health_route(state: AppState) -> Router {
+    Router::new()
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+        .with_state(state)
+}
+
+/// The process is up and serving requests; dependencies are not checked.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, body = HealthResponse))
+)]
+async fn liveness() -> Json<HealthResponse> {
+    Json(HealthResponse { status: "ok" })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, body = ReadinessResponse),
+        (status = 503, body = ReadinessResponse, description = "At least one check is down"),
+    )
+)]
+async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let (database, migrations) = tokio::join!(check_database(&state), check_migrations(&state));
+    let checks = ReadinessChecks {
+        database,
+        migrations,
+        signing_keys: check_signing_keys(&state),
+    };
+
+    let ready = checks.database.is_up() && checks.migrations.is_up() && checks.signing_keys.is_up();
+    let (code, status) = match ready {
+        true => (StatusCode::OK, "ready"),
+        false => (StatusCode::SERVICE_UNAVAILABLE, "not_ready"),
+    };
+    (code, Json(ReadinessResponse { status, checks }))
+}
+
+async fn check_database(state: &AppState) -> ComponentCheck {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, state.readiness.ping()).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("no response within {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    ComponentCheck::from_result(started, result, "Database", "unreachable")
+}
+
+async fn check_migrations(state: &AppState) -> ComponentCheck {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, state.readiness.check_migrations()).await
+    {
+        Ok(result) => result,
+        Err(_) => Err(format!("no response within {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    ComponentCheck::from_result(started, result, "Migration", "pending migrations")
+}
+
+/// Round-trips a token through the live `auth` config, so a key that
+/// reloaded into something unusable takes the instance out of rotation.
+fn check_signing_keys(state: &AppState) -> ComponentCheck {
+    let started = Instant::now();
+    let config = state.config.load();
+    let result = token::probe(&config.auth);
+    ComponentCheck::from_result(started, result, "Signing key", "token round trip failed")
+}
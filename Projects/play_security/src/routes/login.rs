@@ -1,27 +1,52 @@
-use crate::auth::token::create_jwt;
+use crate::auth::password::{init_dummy_hash, verify_dummy, verify_password};
+use crate::auth::token::session_hash;
+use crate::database::repository::SessionRepository;
+use crate::middleware::auth::auth_middleware;
 use crate::response::responses::Response;
-use axum::{Json, Router, extract::State, routing::post};
-use bcrypt::verify;
-use chrono::{Duration, Utc};
+use crate::routes::openapi::{
+    BadRequest, InternalError, TooManyRequests, Unauthorized, UnprocessableEntity,
+};
+use crate::state::AppState;
+use crate::telemetry::metrics::record_login;
+use crate::telemetry::redact::{self, Hidden};
+use crate::validation::{ValidatedJson, validate_password_bytes};
+use axum::{Extension, Json, Router, extract::State, middleware, routing::post};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
-use tracing::{error, info};
+use sqlx::FromRow;
+use std::fmt;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+#[derive(Deserialize, Validate, ToSchema)]
 pub struct LoginRequest {
+    #[validate(length(
+        min = 1,
+        max = 50,
+        code = "length",
+        message = "username must be 1 to 50 characters"
+    ))]
     username: String,
+    #[validate(
+        length(min = 1, code = "length", message = "password must be at least 1 character"),
+        custom(function = validate_password_bytes)
+    )]
     password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct LoginResponse {
     pub code: u16,
     pub message: String,
-    pub token: Option<String>,
-    pub data: Option<UserData>,
+    /// Send as `Authorization: Bearer <token>`.
+    pub token: String,
+    pub data: UserData,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Serialize, FromRow, ToSchema)]
+#[schema(as = LoginUserData)]
 pub struct UserData {
     pub username: String,
     pub name: String,
@@ -30,122 +55,207 @@ pub struct UserData {
     pub email: String,
     #[serde(skip_serializing)]
     pub pwd: String,
+    #[serde(skip_serializing)]
+    pub active: Option<bool>,
+}
+
+impl fmt::Debug for LoginRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginRequest")
+            .field("username", &self.username)
+            .field("password", &Hidden)
+            .finish()
+    }
+}
+
+impl fmt::Debug for LoginResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginResponse")
+            .field("code", &self.code)
+            .field("message", &self.message)
+            .field("token", &Hidden)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserData")
+            .field("username", &self.username)
+            .field("name", &redact::name(&self.name))
+            .field("surname", &redact::name(&self.surname))
+            .field("phone", &redact::phone(&self.phone))
+            .field("email", &redact::email(&self.email))
+            .field("pwd", &Hidden)
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LogoutResponse {
+    pub code: u16,
+    pub message: String,
 }
 
-pub fn login_route(pool: PgPool) -> Router {
-    Router::new().route("/login", post(login)).with_state(pool)
+pub fn login_route(state: AppState) -> Router {
+    init_dummy_hash();
+    let logout = Router::new()
+        .route("/logout", post(logout))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+    Router::new()
+        .route("/login", post(login))
+        .with_state(state)
+        .merge(logout)
 }
 
+/// Every credential failure gets the same `invalid_credentials` problem, so
+/// the response does not reveal whether the username exists.
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 401, response = Unauthorized),
+        (status = 422, response = UnprocessableEntity),
+        (status = 429, response = TooManyRequests),
+        (status = 500, response = InternalError),
+    )
+)]
 pub async fn login(
-    State(pool): State<PgPool>,
-    Json(payload): Json<LoginRequest>,
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
 ) -> Result<Json<LoginResponse>, Response> {
     let LoginRequest { username, password } = payload;
-    let user: Option<UserData> = match sqlx::query_as::<_, UserData>(FETCH_USER_DATA)
-        .bind(&username)
-        .fetch_optional(&pool)
-        .await
+    let AppState {
+        users,
+        sessions,
+        config,
+        login_limiter,
+        ..
+    } = state;
+    let config = config.load();
+
+    if let Err(retry_after) =
+        login_limiter.attempt(&username, config.rate_limit.login_attempts_per_minute)
     {
+        audit_login_failure(&username, "rate_limited");
+        return Err(Response::TooManyRequests(retry_after.as_secs().max(1)));
+    }
+    let user: Option<UserData> = match users.find_credentials(&username).await {
         Ok(r) => r,
-        Err(_) => {
-            error!(
-                "LOGIN: Failed to fetch user data with error: {:?}",
-                Response::InternalError
-            );
-            return Err(Response::InternalError);
+        Err(err) => {
+            error!("LOGIN: Failed to fetch user data with error: {:?}", err);
+            return Err(err.into());
         }
     };
 
-    let user_data = if let Some(user) = user {
-        user
-    } else {
-        error!(
-            "LOGIN: Username provided was incorrect with this error {:?}",
-            Response::Unauthorized
-        );
-        return Ok(Json(LoginResponse {
-            code: Response::Unauthorized.status_code().as_u16(),
-            message: "The username provided is incorrect".to_string(),
-            token: None,
-            data: None,
-        }));
+    // Unknown users still pay for a bcrypt verification so response time does
+    // not reveal which usernames exist.
+    let user_data = match user {
+        Some(user) => user,
+        None => {
+            verify_dummy(&password);
+            audit_login_failure(&username, "unknown_user");
+            return Err(Response::InvalidCredentials);
+        }
     };
 
-    let pwd_valid = match verify(password, user_data.pwd.as_str()) {
+    let pwd_valid = match verify_password(&password, &user_data.pwd) {
         Ok(valid) => valid,
-        Err(_) => {
-            error!(
-                "LOGIN: Password verification failed with error {:?}",
-                Response::InternalError
-            );
+        Err(e) => {
+            error!("LOGIN: Password verification failed with error {:?}", e);
+            audit_login_failure(&username, "unverifiable_hash");
             return Err(Response::InternalError);
         }
     };
 
     if !pwd_valid {
-        error!(
-            "LOGIN: Password provided was incorrect with this error {:?}",
-            Response::Unauthorized
-        );
-        return Ok(Json(LoginResponse {
-            code: Response::Unauthorized.status_code().as_u16(),
-            message: "The password provided is incorrect".to_string(),
-            token: None,
-            data: None,
-        }));
+        audit_login_failure(&username, "bad_password");
+        return Err(Response::InvalidCredentials);
+    }
+
+    if user_data.active == Some(false) {
+        audit_login_failure(&username, "inactive_account");
+        return Err(Response::InvalidCredentials);
     }
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "STAR_WARS_ROCKS".to_string());
-    let token = match create_jwt(&username, &secret).await {
+
+    let ttl = config.auth.token_ttl();
+    let token = match config.auth.token_issuer().issue(&username, &config.auth) {
         Ok(t) => t,
         Err(e) => {
-            error!("LOGIN: Failed to create JWT token: {:?}", e);
+            error!("LOGIN: Failed to create token: {:?}", e);
             return Err(Response::InternalError);
         }
     };
     let now = Utc::now();
-    let expires_at = now + Duration::hours(8);
-
-    match sqlx::query(UPSERT_USER_LOGIN)
-        .bind(&username)
-        .bind(&token)
-        .bind(now)
-        .bind(expires_at)
-        .execute(&pool)
-        .await
-    {
-        Ok(_) => {
-            info!("LOGIN: Token saved for user '{}'", username);
-        }
-        Err(e) => {
-            error!("LOGIN: Failed to save token to database: {:?}", e);
-        }
+    let expires_at = now + ttl;
+
+    // Without a stored session the token would be rejected on first use.
+    let token_hash = session_hash(&token, config.auth.session_hash_key.expose());
+    if let Err(e) = sessions.save(&username, &token_hash, now, expires_at).await {
+        error!("LOGIN: Failed to save session to database: {:?}", e);
+        audit_login_failure(&username, "session_write_failed");
+        return Err(Response::InternalError);
     }
+    info!("LOGIN: Session saved for user '{}'", username);
+    login_limiter.reset(&username);
 
+    info!(target: "audit", username = %username, "LOGIN_SUCCEEDED");
+    record_login("success", "ok");
     Ok(Json(LoginResponse {
         code: Response::Success.status_code().as_u16(),
         message: "User logged in successfully".to_string(),
-        token: Some(token),
-        data: Some(user_data),
+        token,
+        data: user_data,
     }))
 }
 
-const FETCH_USER_DATA: &str = "
-SELECT username
-    ,name
-    ,surname
-    ,phone
-    ,email
-    ,pwd
-FROM users
-WHERE username = $1
-";
-
-const UPSERT_USER_LOGIN: &str = "
-INSERT INTO user_login (username, token, created_datetime, expire_datetime)
-VALUES ($1, $2, $3, $4)
-ON CONFLICT (username)
-DO UPDATE SET
-    token = EXCLUDED.token,
-    created_datetime = EXCLUDED.created_datetime,
-    expire_datetime = EXCLUDED.expire_datetime
-";
+/// Failure reasons are only ever written to the audit log; the client always
+/// gets the same `invalid_credentials` response.
+fn audit_login_failure(username: &str, reason: &'static str) {
+    warn!(target: "audit", username = %username, reason, "LOGIN_FAILED");
+    record_login("failure", reason);
+}
+
+/// Ends the caller's session. The token is rejected from then on, by every
+/// instance.
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = LogoutResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 500, response = InternalError),
+    )
+)]
+pub async fn logout(
+    State(sessions): State<Arc<dyn SessionRepository>>,
+    user: Option<Extension<String>>,
+) -> Result<Json<LogoutResponse>, Response> {
+    // Service certificates authenticate every request; there is no session.
+    let Some(Extension(username)) = user else {
+        return Err(Response::BadRequest.with_detail("Only bearer token sessions can log out"));
+    };
+
+    if let Err(e) = sessions.revoke(std::slice::from_ref(&username)).await {
+        error!("LOGOUT: Failed to revoke session: {:?}", e);
+        return Err(Response::InternalError);
+    }
+
+    info!(target: "audit", username = %username, "LOGOUT");
+    Ok(Json(LogoutResponse {
+        code: Response::Success.status_code().as_u16(),
+        message: "User logged out successfully".to_string(),
+    }))
+}
@@ -1,4 +1,7 @@
 pub mod error;
+pub mod health;
 pub mod home;
 pub mod login;
+pub mod openapi;
+pub mod setup;
 pub mod users;
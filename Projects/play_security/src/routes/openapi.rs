@@ -0,0 +1,146 @@
+use crate::response::problem::Problem;
+use crate::routes::{health, login, setup, users};
+use crate::validation::FieldError;
+use axum::Router;
+use serde::Serialize;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToResponse, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+const BEARER_AUTH: &str = "bearer_auth";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "play_security",
+        description = "User management API. Errors are RFC 7807 `application/problem+json` bodies; match on `code`, not on `title`."
+    ),
+    paths(
+        login::login,
+        login::logout,
+        users::get_all_users,
+        users::create_user,
+        users::get_user,
+        users::update_user,
+        users::remove_user,
+        users::update_password,
+        users::remove_multiple_users,
+        setup::setup,
+        health::liveness,
+        health::readiness,
+    ),
+    components(
+        schemas(Problem, ValidationProblem, FieldError),
+        responses(
+            BadRequest,
+            Unauthorized,
+            Forbidden,
+            UserNotFound,
+            Conflict,
+            UnprocessableEntity,
+            TooManyRequests,
+            InternalError,
+        )
+    ),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Sign in and out"),
+        (name = "users", description = "User accounts, bearer token or service certificate required"),
+        (name = "setup", description = "First admin account, only while none exists"),
+        (name = "health", description = "Probes for load balancers and orchestrators"),
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            BEARER_AUTH,
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "Token returned by `POST /login`: a JWT, a PASETO v4 token or an opaque token depending on `auth.token_format`",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Problem returned when the request body fails validation, with one entry
+/// per failing rule.
+#[derive(Serialize, ToSchema)]
+#[allow(dead_code)]
+pub struct ValidationProblem {
+    #[serde(flatten)]
+    problem: Problem,
+    errors: Vec<FieldError>,
+}
+
+/// Malformed body or path parameter (`malformed_body`, `invalid_path`,
+/// `bad_request`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct BadRequest(Problem);
+
+/// Missing, expired or revoked token (`unauthorized`, `invalid_token`,
+/// `invalid_credentials`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct Unauthorized(Problem);
+
+/// The calling service is not allowed to do this (`forbidden`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct Forbidden(Problem);
+
+/// No matching user (`user_not_found`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct UserNotFound(Problem);
+
+/// A unique field is already taken (`duplicate_value`, with `field`), or
+/// setup has already been completed (`setup_already_completed`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct Conflict(Problem);
+
+/// Validation failed (`validation_failed`, with `errors`) or the database
+/// rejected the values (`constraint_violation`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct UnprocessableEntity(ValidationProblem);
+
+/// Too many login attempts for this username (`rate_limited`, with
+/// `retry_after_secs`).
+#[derive(ToResponse)]
+#[response(
+    content_type = "application/problem+json",
+    headers(("Retry-After" = u64, description = "Seconds until the next attempt is allowed"))
+)]
+#[allow(dead_code)]
+pub struct TooManyRequests(Problem);
+
+/// Unexpected server error (`internal_error`).
+#[derive(ToResponse)]
+#[response(content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct InternalError(Problem);
+
+/// `GET /openapi.json` and the Swagger UI at `/docs`.
+pub fn openapi_route() -> Router {
+    let mut doc = ApiDoc::openapi();
+    // Taken from Cargo.toml, which declares no license.
+    doc.info.license = None;
+    SwaggerUi::new("/docs").url("/openapi.json", doc).into()
+}
@@ -0,0 +1,94 @@
+use crate::database::bootstrap::{AdminAccount, BootstrapError, SetupToken, create_first_admin};
+use crate::database::repository::UserRepository;
+use crate::response::problem::Problem;
+use crate::response::responses::Response;
+use crate::routes::openapi::{
+    BadRequest, Conflict, InternalError, Unauthorized, UnprocessableEntity,
+};
+use crate::routes::users::UserCrudResponse;
+use crate::validation::ValidatedJson;
+use axum::{
+    Json, Router,
+    extract::{FromRef, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+#[derive(Clone, FromRef)]
+pub struct SetupState {
+    pub users: Arc<dyn UserRepository>,
+    pub token: SetupToken,
+}
+
+/// Only mounted while no admin exists and none was configured.
+pub fn setup_route(users: Arc<dyn UserRepository>, token: SetupToken) -> Router {
+    Router::new()
+        .route("/setup", post(setup))
+        .with_state(SetupState { users, token })
+}
+
+#[utoipa::path(
+    post,
+    path = "/setup",
+    tag = "setup",
+    params(("X-Setup-Token" = String, Header, description = "Token printed in the server log at startup")),
+    request_body = AdminAccount,
+    responses(
+        (status = 200, body = UserCrudResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 409, response = Conflict),
+        (status = 422, response = UnprocessableEntity),
+        (status = 500, response = InternalError),
+    )
+)]
+async fn setup(
+    State(state): State<SetupState>,
+    headers: HeaderMap,
+    ValidatedJson(account): ValidatedJson<AdminAccount>,
+) -> Result<Json<UserCrudResponse>, Response> {
+    let provided = headers
+        .get("X-Setup-Token")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default();
+    if !state.token.matches(provided) {
+        warn!(target: "audit", "SETUP_REJECTED");
+        return Err(Response::Unauthorized.with_detail("Invalid or expired setup token"));
+    }
+    if account.uses_known_default() {
+        return Err(Response::BadRequest.with_detail("The password is a known default"));
+    }
+
+    match create_first_admin(state.users.as_ref(), &account).await {
+        Ok(true) => {
+            state.token.consume();
+            info!(target: "audit", username = %account.username, "SETUP_COMPLETED");
+            Ok(Json(UserCrudResponse {
+                code: Response::Success.status_code().as_u16(),
+                message: format!("Admin: {} was successfully created", account.username),
+                rows_affected: 1,
+            }))
+        }
+        Ok(false) => {
+            state.token.consume();
+            Err(Response::Problem(Box::new(
+                Problem::new(
+                    StatusCode::CONFLICT,
+                    "setup_already_completed",
+                    "Setup Already Completed",
+                )
+                .with_detail("An admin account already exists"),
+            )))
+        }
+        Err(BootstrapError::Db(e)) => {
+            error!("SETUP: failed with error: {:?}", e);
+            Err(e.into())
+        }
+        Err(e) => {
+            error!("SETUP: failed with error: {:?}", e);
+            Err(Response::InternalError)
+        }
+    }
+}
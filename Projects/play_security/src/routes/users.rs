@@ -1,18 +1,32 @@
+use crate::auth::password::hash_password;
+use crate::database::repository::{SessionRepository, UserRepository};
+use crate::extract::Path;
 use crate::middleware::auth::auth_middleware;
 use crate::response::responses::Response;
+use crate::routes::openapi::{
+    BadRequest, Conflict, Forbidden, InternalError, Unauthorized, UnprocessableEntity, UserNotFound,
+};
+use crate::state::AppState;
+use crate::telemetry::redact;
+use crate::validation::{
+    PHONE_RE, USERNAME_RE, ValidatedJson, validate_ids, validate_password_bytes,
+};
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::State,
     middleware,
     routing::{delete, get, patch, post, put},
 };
-use bcrypt::{DEFAULT_COST, hash};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::FromRow;
+use std::fmt;
+use std::sync::Arc;
 use tracing::{error, info};
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AllUserFetchResponse {
     pub code: u16,
     pub message: String,
@@ -20,47 +34,122 @@ pub struct AllUserFetchResponse {
     pub total: u64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct SingleUserGetResponse {
     pub code: u16,
     pub message: String,
     pub data: Option<UserData>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct UserCrudResponse {
     pub code: u16,
     pub message: String,
     pub rows_affected: u64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate, ToSchema)]
 pub struct MultipleUsersRequest {
+    #[validate(
+        length(min = 1, max = 100, code = "length", message = "ids must contain 1 to 100 entries"),
+        custom(function = validate_ids)
+    )]
     pub ids: Vec<i32>,
 }
-#[derive(Deserialize)]
+
+#[derive(Deserialize, Validate, ToSchema)]
 pub struct UserPasswordChange {
+    #[validate(
+        length(min = 8, code = "length", message = "password must be at least 8 characters"),
+        custom(function = validate_password_bytes)
+    )]
     pub password: String,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        code = "length",
+        message = "update_by must be 1 to 50 characters"
+    ))]
     pub update_by: String,
 }
 
-#[derive(Debug, FromRow, Serialize, Deserialize)]
+// Length limits mirror the VARCHAR sizes of the users table. `id` and
+// `create_date` are accepted but ignored on input; utoipa drops fields marked
+// `skip_deserializing` from the schema altogether.
+#[derive(FromRow, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UserData {
-    #[serde(skip_deserializing)]
+    #[serde(default)]
+    #[schema(read_only)]
     pub id: i32,
+    #[validate(
+        length(min = 3, max = 50, code = "length", message = "username must be 3 to 50 characters"),
+        regex(path = *USERNAME_RE, code = "invalid_username", message = "username may only contain letters, digits, '.', '_' and '-'")
+    )]
     pub username: String,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        code = "length",
+        message = "name must be 1 to 50 characters"
+    ))]
     pub name: String,
+    #[validate(length(
+        min = 1,
+        max = 70,
+        code = "length",
+        message = "surname must be 1 to 70 characters"
+    ))]
     pub surname: String,
+    #[validate(regex(path = *PHONE_RE, code = "invalid_phone", message = "phone must be in E.164 format, e.g. +27821234567"))]
+    #[schema(example = "+27821234567")]
     pub phone: String,
+    #[validate(
+        email(code = "invalid_email", message = "email must be a valid address"),
+        length(
+            max = 100,
+            code = "length",
+            message = "email must be at most 100 characters"
+        )
+    )]
     pub email: String,
-    #[serde(skip_deserializing)]
+    #[serde(default)]
+    #[schema(read_only)]
     pub create_date: chrono::NaiveDateTime,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        code = "length",
+        message = "created_by must be 1 to 50 characters"
+    ))]
     pub created_by: String,
     pub write_date: Option<chrono::NaiveDateTime>,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        code = "length",
+        message = "update_by must be 1 to 50 characters"
+    ))]
     pub update_by: Option<String>,
 }
 
-pub fn users_route(pool: PgPool) -> Router {
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserData")
+            .field("id", &self.id)
+            .field("username", &self.username)
+            .field("name", &redact::name(&self.name))
+            .field("surname", &redact::name(&self.surname))
+            .field("phone", &redact::phone(&self.phone))
+            .field("email", &redact::email(&self.email))
+            .field("create_date", &self.create_date)
+            .field("created_by", &self.created_by)
+            .field("write_date", &self.write_date)
+            .field("update_by", &self.update_by)
+            .finish()
+    }
+}
+
+pub fn users_route(state: AppState) -> Router {
     Router::new()
         .route("/users", get(get_all_users))
         .route("/users", post(create_user))
@@ -69,22 +158,31 @@ pub fn users_route(pool: PgPool) -> Router {
         .route("/users/:id", delete(remove_user))
         .route("/users/update_pwd/:id", patch(update_password))
         .route("/users/delete_multiple", delete(remove_multiple_users))
-        .with_state(pool.clone())
-        .route_layer(middleware::from_fn_with_state(pool, auth_middleware))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(state, auth_middleware))
 }
 
-async fn get_all_users(State(pool): State<PgPool>) -> Result<Json<AllUserFetchResponse>, Response> {
-    let users: Vec<UserData> = match sqlx::query_as::<_, UserData>(GET_ALL_USERS)
-        .fetch_all(&pool)
-        .await
-    {
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = AllUserFetchResponse),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 404, response = UserNotFound),
+        (status = 500, response = InternalError),
+    )
+)]
+async fn get_all_users(
+    State(users): State<Arc<dyn UserRepository>>,
+) -> Result<Json<AllUserFetchResponse>, Response> {
+    let users: Vec<UserData> = match users.list().await {
         Ok(u) => u,
-        Err(_) => {
-            error!(
-                "GET_ALL_USERS failed with error: {:?}",
-                Response::InternalError
-            );
-            return Err(Response::InternalError);
+        Err(err) => {
+            error!("GET_ALL_USERS failed with error: {:?}", err);
+            return Err(err.into());
         }
     };
     info!("GET_ALL_USERS: {:?}", users);
@@ -104,19 +202,30 @@ async fn get_all_users(State(pool): State<PgPool>) -> Result<Json<AllUserFetchRe
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = SingleUserGetResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 404, response = UserNotFound),
+        (status = 500, response = InternalError),
+    )
+)]
 async fn get_user(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
 ) -> Result<Json<SingleUserGetResponse>, Response> {
-    let user: Option<UserData> = match sqlx::query_as::<_, UserData>(FETCH_SINGLE_USER)
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-    {
+    let user: Option<UserData> = match users.find(id).await {
         Ok(u) => u,
-        Err(_) => {
-            error!("GET_USER failed with error: {:?}", Response::InternalError);
-            return Err(Response::InternalError);
+        Err(err) => {
+            error!("GET_USER failed with error: {:?}", err);
+            return Err(err.into());
         }
     };
     info!("GET_USER: {:?}", user);
@@ -132,101 +241,133 @@ async fn get_user(
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = UserCrudResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 404, response = UserNotFound),
+        (status = 422, response = UnprocessableEntity),
+        (status = 500, response = InternalError),
+    )
+)]
 async fn remove_user(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
+    State(users): State<Arc<dyn UserRepository>>,
+    State(sessions): State<Arc<dyn SessionRepository>>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    let result = match sqlx::query(REMOVE_USER).bind(id).execute(&pool).await {
-        Ok(u) => u,
-        Err(_) => {
-            error!(
-                "REMOVE_USER: Record id: {id} failed with error: {:?}",
-                Response::InternalError
-            );
-            return Err(Response::InternalError);
+    let removed = match users.remove(id).await {
+        Ok(removed) => removed,
+        Err(err) => {
+            error!("REMOVE_USER: Record id: {id} failed with error: {:?}", err);
+            return Err(err.into());
         }
     };
 
-    if result.rows_affected() == 0 {
+    let Some(username) = removed else {
         error!(
             "REMOVE_USER: Record id: {id} failed with error: {:?}, rows affected 0",
             Response::NoUserFound
         );
         return Err(Response::NoUserFound);
-    }
-    info!(
-        "REMOVE_USER: Record id: {id} successfully removed: {:?}",
-        result
-    );
+    };
+    end_sessions(sessions.as_ref(), &[username]).await;
+    info!("REMOVE_USER: Record id: {id} successfully removed, rows affected 1");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: "User was successfully deleted".to_string(),
-        rows_affected: result.rows_affected(),
+        rows_affected: 1,
     }))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/users/delete_multiple",
+    tag = "users",
+    request_body = MultipleUsersRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = UserCrudResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 404, response = UserNotFound),
+        (status = 422, response = UnprocessableEntity),
+        (status = 500, response = InternalError),
+    )
+)]
 async fn remove_multiple_users(
-    State(pool): State<PgPool>,
-    Json(payload): Json<MultipleUsersRequest>,
+    State(users): State<Arc<dyn UserRepository>>,
+    State(sessions): State<Arc<dyn SessionRepository>>,
+    ValidatedJson(payload): ValidatedJson<MultipleUsersRequest>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    if payload.ids.is_empty() {
-        error!(
-            "MULTI_REMOVE_USER: {:?}, payload empty",
-            Response::BadRequest
-        );
-        return Err(Response::BadRequest);
-    }
-
-    let result = match sqlx::query(REMOVE_MULTIPLE_USERS)
-        .bind(&payload.ids)
-        .execute(&pool)
-        .await
-    {
-        Ok(u) => u,
-        Err(_) => {
-            error!(
-                "MULTI_REMOVE_USER: failed with error: {:?}",
-                Response::InternalError
-            );
-            return Err(Response::InternalError);
+    let removed = match users.remove_many(&payload.ids).await {
+        Ok(removed) => removed,
+        Err(err) => {
+            error!("MULTI_REMOVE_USER: failed with error: {:?}", err);
+            return Err(err.into());
         }
     };
 
-    if result.rows_affected() == 0 {
+    if removed.is_empty() {
         error!(
             "MULTI_REMOVE_USER: failed with error: {:?}, rows affected 0",
             Response::NoUserFound
         );
         return Err(Response::NoUserFound);
     }
+    end_sessions(sessions.as_ref(), &removed).await;
+    let rows_affected = removed.len() as u64;
     info!(
-        "MULTI_REMOVE_USER: Users successfully removed: {:?}",
-        result
+        "MULTI_REMOVE_USER: Users successfully removed, rows affected {}",
+        rows_affected
     );
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: "Successfully Deleted Users".to_string(),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
 
+/// Removed users' tokens already fail the session lookup, so this only
+/// clears their rows and cached sessions; a failure is logged, not returned.
+async fn end_sessions(sessions: &dyn SessionRepository, usernames: &[String]) {
+    if let Err(err) = sessions.revoke(usernames).await {
+        error!("REMOVE_USER: Failed to revoke sessions: {:?}", err);
+    }
+}
+
+/// The new user's initial password is `<username>#01!`.
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "users",
+    request_body = UserData,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = UserCrudResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 409, response = Conflict),
+        (status = 422, response = UnprocessableEntity),
+        (status = 500, response = InternalError),
+    )
+)]
 async fn create_user(
-    State(pool): State<PgPool>,
-    Json(payload): Json<UserData>,
+    State(users): State<Arc<dyn UserRepository>>,
+    ValidatedJson(payload): ValidatedJson<UserData>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    let UserData {
-        username,
-        name,
-        surname,
-        phone,
-        email,
-        created_by,
-        ..
-    } = payload;
-
-    let plain_pwd: String = format!("{}#01!", &username);
+    let username = &payload.username;
+    let plain_pwd: String = format!("{}#01!", username);
 
-    let hashed_pwd = match hash(plain_pwd, DEFAULT_COST) {
+    let hashed_pwd = match hash_password(&plain_pwd) {
         Ok(r) => r,
         Err(_) => {
             error!(
@@ -237,74 +378,66 @@ async fn create_user(
         }
     };
 
-    let result = match sqlx::query(CREATE_USER)
-        .bind(&username)
-        .bind(name)
-        .bind(surname)
-        .bind(phone)
-        .bind(email)
-        .bind(hashed_pwd)
-        .bind(created_by)
-        .execute(&pool)
-        .await
-    {
-        Ok(r) => r,
-        Err(_) => {
-            error!(
-                "CREATE_USER: failed with error: {:?}",
-                Response::InternalError
-            );
-            return Err(Response::InternalError);
+    let rows_affected = match users.create(&payload, &hashed_pwd).await {
+        Ok(n) => n,
+        Err(err) => {
+            error!("CREATE_USER: failed with error: {:?}", err);
+            return Err(err.into());
         }
     };
     info!("CREATE USER: The user: {username} was successfully created");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: format!("User: {username} was successfully created"),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
 
+/// Renaming the user also ends their session, so they have to log in again.
+#[utoipa::path(
+    put,
+    path = "/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    request_body = UserData,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = UserCrudResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 404, response = UserNotFound),
+        (status = 409, response = Conflict),
+        (status = 422, response = UnprocessableEntity),
+        (status = 500, response = InternalError),
+    )
+)]
 async fn update_user(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
-    Json(payload): Json<UserData>,
+    State(users): State<Arc<dyn UserRepository>>,
+    State(sessions): State<Arc<dyn SessionRepository>>,
+    ValidatedJson(payload): ValidatedJson<UserData>,
 ) -> Result<Json<UserCrudResponse>, Response> {
-    let UserData {
-        username,
-        name,
-        surname,
-        phone,
-        email,
-        update_by,
-        ..
-    } = payload;
-
+    let username = &payload.username;
     let write_date = Utc::now().naive_utc();
 
-    let result = match sqlx::query(UPDATE_USER)
-        .bind(&username)
-        .bind(name)
-        .bind(surname)
-        .bind(phone)
-        .bind(email)
-        .bind(update_by)
-        .bind(write_date)
-        .bind(id)
-        .execute(&pool)
-        .await
-    {
-        Ok(r) => r,
-        Err(_) => {
-            error!(
-                "UPDATE_USER: Record id: {id} failed with error:  {:?}",
-                Response::InternalError
-            );
-            return Err(Response::InternalError);
+    let previous = match users.find(id).await {
+        Ok(user) => user.map(|u| u.username),
+        Err(err) => {
+            error!("UPDATE_USER: Record id: {id} failed with error:  {:?}", err);
+            return Err(err.into());
+        }
+    };
+
+    let rows_affected = match users.update(id, &payload, write_date).await {
+        Ok(n) => n,
+        Err(err) => {
+            error!("UPDATE_USER: Record id: {id} failed with error:  {:?}", err);
+            return Err(err.into());
         }
     };
 
-    if result.rows_affected() == 0 {
+    if rows_affected == 0 {
         error!(
             "UPDATE_USER: Record id: {id} failed with error: {:?} rows affected 0",
             Response::InternalError
@@ -312,18 +445,48 @@ async fn update_user(
         return Err(Response::NoUserFound);
     }
 
+    // Sessions are keyed by username, so a rename logs the user out
+    // everywhere rather than leaving them under the old name.
+    if let Some(previous) = previous.filter(|previous| previous != username)
+        && let Err(err) = sessions.revoke(&[previous]).await
+    {
+        error!(
+            "UPDATE_USER: Failed to revoke sessions for record id {id}: {:?}",
+            err
+        );
+        return Err(err.into());
+    }
+
     info!("User: {username} updated successfully");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: format!("User: {username} updated successfully"),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
 
+/// Also ends the user's session, so they have to log in again.
+#[utoipa::path(
+    patch,
+    path = "/users/update_pwd/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    request_body = UserPasswordChange,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = UserCrudResponse),
+        (status = 400, response = BadRequest),
+        (status = 401, response = Unauthorized),
+        (status = 403, response = Forbidden),
+        (status = 422, response = UnprocessableEntity),
+        (status = 500, response = InternalError),
+    )
+)]
 async fn update_password(
     Path(id): Path<i32>,
-    State(pool): State<PgPool>,
-    Json(payload): Json<UserPasswordChange>,
+    State(users): State<Arc<dyn UserRepository>>,
+    State(sessions): State<Arc<dyn SessionRepository>>,
+    ValidatedJson(payload): ValidatedJson<UserPasswordChange>,
 ) -> Result<Json<UserCrudResponse>, Response> {
     let UserPasswordChange {
         password,
@@ -335,7 +498,7 @@ async fn update_password(
     }
 
     let write_date = Utc::now().naive_utc();
-    let hashed_pwd = match hash(password, DEFAULT_COST) {
+    let hashed_pwd = match hash_password(&password) {
         Ok(r) => r,
         Err(_) => {
             error!(
@@ -346,93 +509,43 @@ async fn update_password(
         }
     };
 
-    let result = match sqlx::query(UPDATE_USER_PWD)
-        .bind(hashed_pwd)
-        .bind(update_by)
-        .bind(write_date)
-        .bind(id)
-        .execute(&pool)
-        .await
-    {
-        Ok(r) => r,
-        Err(_) => {
+    let username = match users.find(id).await {
+        Ok(user) => user.map(|u| u.username),
+        Err(err) => {
             error!(
-                "UPDATE_PASSWOR: Record id {id} failed with error {:?}",
-                Response::InternalError
+                "UPDATE_PASSWORD: Record id {id} failed with error {:?}",
+                err
             );
-            return Err(Response::InternalError);
+            return Err(err.into());
         }
     };
 
+    let rows_affected = match users
+        .update_password(id, &hashed_pwd, &update_by, write_date)
+        .await
+    {
+        Ok(n) => n,
+        Err(err) => {
+            error!("UPDATE_PASSWOR: Record id {id} failed with error {:?}", err);
+            return Err(err.into());
+        }
+    };
+
+    // A password change logs the user out everywhere.
+    if let Some(username) = username.filter(|_| rows_affected > 0)
+        && let Err(err) = sessions.revoke(&[username]).await
+    {
+        error!(
+            "UPDATE_PASSWORD: Failed to revoke sessions for record id {id}: {:?}",
+            err
+        );
+        return Err(err.into());
+    }
+
     info!("User password for record: {id} updated successfully");
     Ok(Json(UserCrudResponse {
         code: Response::Success.status_code().as_u16(),
         message: "Password updated successfully".to_string(),
-        rows_affected: result.rows_affected(),
+        rows_affected,
     }))
 }
-
-const GET_ALL_USERS: &str = "
-SELECT id
-    ,username
-    ,name
-    ,surname
-    ,phone
-    ,email
-    ,create_date
-    ,created_by
-    ,write_date
-    ,update_by
-FROM users 
-ORDER BY id
-";
-
-const FETCH_SINGLE_USER: &str = "
-SELECT id
-    ,username
-    ,name
-    ,surname
-    ,phone
-    ,email
-    ,create_date
-    ,created_by
-    ,write_date
-    ,update_by
-FROM users 
-WHERE id = $1
-";
-
-const REMOVE_USER: &str = "
-DELETE FROM users
-WHERE id = $1
-";
-
-const REMOVE_MULTIPLE_USERS: &str = "
-DELETE FROM users
-WHERE id = ANY($1)
-";
-
-const CREATE_USER: &str = "
-INSERT INTO users (username, name, surname, phone, email, pwd, created_by)
-VALUES($1, $2, $3, $4, $5, $6, $7)
-";
-
-const UPDATE_USER: &str = "
-UPDATE users
-SET username = $1
-    ,name = $2
-    ,surname = $3
-    ,phone = $4
-    ,email = $5
-    ,update_by = $6
-    ,write_date = $7
-WHERE id = $8
-";
-
-const UPDATE_USER_PWD: &str = "
-UPDATE users
-SET pwd = $1
-    ,update_by = $2
-    ,write_date = $3
-WHERE id = $4
-";
@@ -0,0 +1,134 @@
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+
+/// Exit status when requests were still running at the shutdown deadline.
+pub const EXIT_DRAIN_TIMEOUT: u8 = 2;
+
+/// Coordinates shutdown: listeners stop accepting once it is triggered and
+/// every task started through `spawn` is cancelled and awaited.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+    triggered_at: Arc<OnceLock<Instant>>,
+}
+
+pub enum Drained {
+    Clean,
+    DeadlineExceeded,
+}
+
+impl Shutdown {
+    /// Triggers shutdown on the first Ctrl-C or SIGTERM.
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            info!("SHUTDOWN: Received {signal}, no longer accepting connections");
+            shutdown.trigger();
+        });
+    }
+
+    /// Runs `task` until it finishes or shutdown starts, whichever is first.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let token = self.token.clone();
+        self.tasks.spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = task => {}
+            }
+        });
+    }
+
+    fn trigger(&self) {
+        self.triggered_at.get_or_init(Instant::now);
+        self.token.cancel();
+    }
+
+    pub fn triggered(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.token.clone().cancelled_owned()
+    }
+
+    /// Drives `server` until it has drained after shutdown was triggered,
+    /// giving up on open connections once `deadline` has passed.
+    pub async fn drain<F>(&self, server: F, deadline: Duration) -> std::io::Result<Drained>
+    where
+        F: Future<Output = std::io::Result<()>>,
+    {
+        let expired = async {
+            self.token.cancelled().await;
+            tokio::time::sleep(deadline).await;
+        };
+        tokio::select! {
+            result = server => result.map(|_| Drained::Clean),
+            _ = expired => {
+                warn!(
+                    "SHUTDOWN: Connections still open after {}s, closing them",
+                    deadline.as_secs()
+                );
+                Ok(Drained::DeadlineExceeded)
+            }
+        }
+    }
+
+    /// Cancels and waits for every task started through `spawn`, then runs
+    /// `cleanup`. Both share what is left of `deadline`, counted from when
+    /// shutdown was triggered, so a stuck request holding a task or a pooled
+    /// connection cannot keep the process alive past it.
+    pub async fn finish<F>(&self, cleanup: F, deadline: Duration) -> Drained
+    where
+        F: Future<Output = ()>,
+    {
+        self.trigger();
+        let elapsed = self
+            .triggered_at
+            .get()
+            .map_or(Duration::ZERO, Instant::elapsed);
+        let remaining = deadline.saturating_sub(elapsed);
+
+        let stop = async {
+            self.tasks.close();
+            self.tasks.wait().await;
+            cleanup.await;
+        };
+        match tokio::time::timeout(remaining, stop).await {
+            Ok(()) => Drained::Clean,
+            Err(_) => {
+                warn!(
+                    "SHUTDOWN: Background tasks or database connections still busy after {}s, exiting",
+                    deadline.as_secs()
+                );
+                Drained::DeadlineExceeded
+            }
+        }
+    }
+}
+
+async fn wait_for_signal() -> &'static str {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut s) => {
+                s.recv().await;
+            }
+            Err(e) => {
+                warn!("SHUTDOWN: SIGTERM handling unavailable: {e}");
+                std::future::pending::<()>().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "Ctrl-C",
+        _ = terminate => "SIGTERM",
+    }
+}
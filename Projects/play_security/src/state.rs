@@ -0,0 +1,16 @@
+use crate::auth::rate_limit::LoginRateLimiter;
+use crate::config::SharedConfig;
+use crate::database::repository::{Readiness, SessionRepository, UserRepository};
+use axum::extract::FromRef;
+use std::sync::Arc;
+
+/// Shared by every router. Handlers that only need one repository can
+/// extract it directly, e.g. `State<Arc<dyn UserRepository>>`.
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub users: Arc<dyn UserRepository>,
+    pub sessions: Arc<dyn SessionRepository>,
+    pub readiness: Arc<dyn Readiness>,
+    pub config: SharedConfig,
+    pub login_limiter: Arc<LoginRateLimiter>,
+}
@@ -0,0 +1,141 @@
+use crate::database::Database;
+use crate::shutdown::Shutdown;
+use axum::{
+    Router,
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response as AxumResponse},
+    routing::get,
+};
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use sqlx::Pool;
+use std::time::{Duration, Instant};
+
+/// Seconds; covers a cached lookup up to a slow bcrypt round.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4";
+
+/// Installs the global Prometheus recorder. Metrics recorded before this
+/// runs, or when it is never called, are dropped.
+pub fn install() -> Result<PrometheusHandle, String> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets(LATENCY_BUCKETS)
+        .map_err(|e| e.to_string())?
+        .install_recorder()
+        .map_err(|e| e.to_string())?;
+
+    describe_counter!("http_requests_total", "HTTP requests by route and status");
+    describe_histogram!(
+        "http_request_duration_seconds",
+        "HTTP request latency by route and status"
+    );
+    describe_counter!(
+        "login_attempts_total",
+        "Login attempts by result and reason"
+    );
+    describe_counter!(
+        "auth_token_validations_total",
+        "Credential checks in auth_middleware by result and reason"
+    );
+    describe_histogram!(
+        "password_hash_duration_seconds",
+        "Time spent in bcrypt by operation"
+    );
+    describe_counter!(
+        "session_cache_lookups_total",
+        "Session checks answered from the session cache (hit) or the database (miss)"
+    );
+    describe_gauge!("db_pool_connections", "Open database connections");
+    describe_gauge!("db_pool_idle_connections", "Idle database connections");
+    describe_gauge!("db_pool_max_connections", "Configured pool size limit");
+    describe_histogram!(
+        "db_pool_acquire_wait_seconds",
+        "Time queries waited to check out a pooled connection"
+    );
+    Ok(handle)
+}
+
+/// Samples the pool, when there is one, and runs exporter upkeep until
+/// shutdown.
+pub fn spawn_upkeep(handle: PrometheusHandle, database: Database, shutdown: &Shutdown) {
+    shutdown.spawn(async move {
+        let mut tick = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            tick.tick().await;
+            handle.run_upkeep();
+            match &database {
+                Database::Postgres(pool) => record_pool(pool),
+                Database::Sqlite(pool) => record_pool(pool),
+                Database::Memory(_) => {}
+            }
+        }
+    });
+}
+
+/// Reads the pool's own counters only; acquire waits are recorded by the
+/// queries themselves, see `record_pool_wait`.
+fn record_pool<DB: sqlx::Database>(pool: &Pool<DB>) {
+    gauge!("db_pool_connections").set(pool.size() as f64);
+    gauge!("db_pool_idle_connections").set(pool.num_idle() as f64);
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+pub fn metrics_route(handle: PrometheusHandle) -> Router {
+    Router::new()
+        .route("/metrics", get(render))
+        .with_state(handle)
+}
+
+async fn render(State(handle): State<PrometheusHandle>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, PROMETHEUS_TEXT)], handle.render())
+}
+
+/// Counts and times every request. The route label is the matched route
+/// pattern, e.g. `/users/:id`, so ids do not create new series.
+pub async fn track_http(req: Request, next: Next) -> AxumResponse {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().to_string();
+    let started = Instant::now();
+
+    let res = next.run(req).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", res.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed().as_secs_f64());
+    res
+}
+
+pub fn record_login(result: &'static str, reason: &'static str) {
+    counter!("login_attempts_total", "result" => result, "reason" => reason).increment(1);
+}
+
+pub fn record_token_validation(result: &'static str, reason: &'static str) {
+    counter!("auth_token_validations_total", "result" => result, "reason" => reason).increment(1);
+}
+
+pub fn record_session_cache(result: &'static str) {
+    counter!("session_cache_lookups_total", "result" => result).increment(1);
+}
+
+pub fn record_password_hash(operation: &'static str, started: Instant) {
+    histogram!("password_hash_duration_seconds", "operation" => operation)
+        .record(started.elapsed().as_secs_f64());
+}
+
+/// Records how long a query waited for a pooled connection.
+pub fn record_pool_wait(started: Instant) {
+    histogram!("db_pool_acquire_wait_seconds").record(started.elapsed().as_secs_f64());
+}
@@ -0,0 +1,3 @@
+pub mod metrics;
+pub mod otel;
+pub mod redact;
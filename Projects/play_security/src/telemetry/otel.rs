@@ -0,0 +1,174 @@
+use crate::config::TracingConfig;
+use axum::http::HeaderMap;
+use opentelemetry::trace::{SpanKind, Status, TracerProvider};
+use opentelemetry::{Context, global};
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider, SpanData, SpanExporter};
+use serde_json::{Map, Value, json};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{Level, Span, Subscriber, info_span};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Appended to `tracing.otlp_endpoint`, as `OTEL_EXPORTER_OTLP_ENDPOINT`
+/// is for other OTLP/HTTP clients.
+const OTLP_TRACES_PATH: &str = "/v1/traces";
+
+/// Builds the tracer provider for the configured exporter, or `None` when
+/// trace export is off. Also installs the W3C `traceparent` propagator.
+pub fn init(config: &TracingConfig) -> Result<Option<SdkTracerProvider>, String> {
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+    let builder = SdkTracerProvider::builder().with_resource(resource);
+
+    let provider = if let Some(endpoint) = &config.otlp_endpoint {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!(
+                "{}{OTLP_TRACES_PATH}",
+                endpoint.trim_end_matches('/')
+            ))
+            .build()
+            .map_err(|e| e.to_string())?;
+        builder.with_batch_exporter(exporter).build()
+    } else if let Some(path) = &config.file_path {
+        // Written synchronously so a test can read the file as soon as the
+        // request has completed.
+        builder
+            .with_simple_exporter(FileExporter::open(path)?)
+            .build()
+    } else {
+        return Ok(None);
+    };
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Some(provider))
+}
+
+pub fn tracer(provider: &SdkTracerProvider) -> SdkTracer {
+    provider.tracer(env!("CARGO_PKG_NAME"))
+}
+
+/// The export layer, with a filter of its own: `log.level` only governs
+/// local logs, so lowering it must not drop the request, query and password
+/// spans from traces.
+pub fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(tracer(provider))
+        .with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::INFO))
+}
+
+/// Trace context sent by the caller in `traceparent`/`tracestate`.
+pub fn remote_context(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+/// Span around one SQL statement. Await the query inside it with
+/// `.instrument(query_span(DB_SYSTEM, SQL))`, where `DB_SYSTEM` is the
+/// OpenTelemetry `db.system` name of the backend.
+pub fn query_span(system: &str, statement: &str) -> Span {
+    let statement = statement.trim();
+    let operation = statement
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    info_span!(
+        "db.query",
+        otel.name = %format_args!("db {operation}"),
+        otel.kind = "client",
+        db.system = system,
+        db.operation = %operation,
+        db.statement = %statement,
+    )
+}
+
+/// Writes each finished span as one JSON object per line.
+#[derive(Debug)]
+struct FileExporter {
+    file: Mutex<File>,
+}
+
+impl FileExporter {
+    fn open(path: &Path) -> Result<FileExporter, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        Ok(FileExporter {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl SpanExporter for FileExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut lines = String::new();
+        for span in &batch {
+            lines.push_str(&span_json(span).to_string());
+            lines.push('\n');
+        }
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| OTelSdkError::InternalFailure("trace file lock poisoned".to_string()))?;
+        file.write_all(lines.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+}
+
+fn span_json(span: &SpanData) -> Value {
+    let attributes: Map<String, Value> = span
+        .attributes
+        .iter()
+        .map(|kv| (kv.key.to_string(), Value::String(kv.value.to_string())))
+        .collect();
+    let (status, status_message) = match &span.status {
+        Status::Unset => ("unset", None),
+        Status::Ok => ("ok", None),
+        Status::Error { description } => ("error", Some(description.to_string())),
+    };
+    json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": span.parent_span_id.to_string(),
+        "name": span.name,
+        "kind": span_kind(&span.span_kind),
+        "start_unix_nano": unix_nanos(span.start_time),
+        "end_unix_nano": unix_nanos(span.end_time),
+        "attributes": attributes,
+        "status": status,
+        "status_message": status_message,
+    })
+}
+
+fn span_kind(kind: &SpanKind) -> &'static str {
+    match kind {
+        SpanKind::Client => "client",
+        SpanKind::Server => "server",
+        SpanKind::Producer => "producer",
+        SpanKind::Consumer => "consumer",
+        SpanKind::Internal => "internal",
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
@@ -0,0 +1,140 @@
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How personal data (names, email addresses, phone numbers) appears in
+/// `Debug` output and therefore in logs. Credentials are never printed,
+/// whatever the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiPolicy {
+    /// `<redacted>`
+    Redact,
+    /// Enough to tell records apart, e.g. `j***@example.com`, `+27*******78`.
+    Partial,
+    /// Unchanged. Only for local debugging.
+    Plain,
+}
+
+impl Default for PiiPolicy {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            PiiPolicy::Partial
+        } else {
+            PiiPolicy::Redact
+        }
+    }
+}
+
+impl FromStr for PiiPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "redact" => Ok(PiiPolicy::Redact),
+            "partial" => Ok(PiiPolicy::Partial),
+            "plain" => Ok(PiiPolicy::Plain),
+            other => Err(format!(
+                "expected 'redact', 'partial' or 'plain', got '{other}'"
+            )),
+        }
+    }
+}
+
+// Debug impls cannot reach the config, so the active policy is global. It
+// starts fully redacted until the config has been loaded.
+static POLICY: AtomicU8 = AtomicU8::new(PiiPolicy::Redact as u8);
+
+pub fn set_policy(policy: PiiPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+fn policy() -> PiiPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        p if p == PiiPolicy::Plain as u8 => PiiPolicy::Plain,
+        p if p == PiiPolicy::Partial as u8 => PiiPolicy::Partial,
+        _ => PiiPolicy::Redact,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Name,
+    Email,
+    Phone,
+}
+
+/// A personal value that formats according to the active `PiiPolicy`.
+pub struct Pii<'a> {
+    value: &'a str,
+    kind: Kind,
+}
+
+pub fn name(value: &str) -> Pii<'_> {
+    Pii {
+        value,
+        kind: Kind::Name,
+    }
+}
+
+pub fn email(value: &str) -> Pii<'_> {
+    Pii {
+        value,
+        kind: Kind::Email,
+    }
+}
+
+pub fn phone(value: &str) -> Pii<'_> {
+    Pii {
+        value,
+        kind: Kind::Phone,
+    }
+}
+
+impl fmt::Debug for Pii<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match policy() {
+            PiiPolicy::Plain => write!(f, "{:?}", self.value),
+            PiiPolicy::Partial => write!(f, "\"{}\"", self.partial()),
+            PiiPolicy::Redact => f.write_str("<redacted>"),
+        }
+    }
+}
+
+impl Pii<'_> {
+    fn partial(&self) -> String {
+        if self.value.is_empty() {
+            return String::new();
+        }
+        match self.kind {
+            Kind::Name => format!("{}***", first_char(self.value)),
+            Kind::Email => match self.value.split_once('@') {
+                Some((local, domain)) => format!("{}***@{domain}", first_char(local)),
+                None => "***".to_string(),
+            },
+            Kind::Phone => {
+                let chars: Vec<char> = self.value.chars().collect();
+                if chars.len() <= 5 {
+                    return "***".to_string();
+                }
+                let head: String = chars[..3].iter().collect();
+                let tail: String = chars[chars.len() - 2..].iter().collect();
+                format!("{head}{}{tail}", "*".repeat(chars.len() - 5))
+            }
+        }
+    }
+}
+
+fn first_char(value: &str) -> String {
+    value.chars().next().map(String::from).unwrap_or_default()
+}
+
+/// Stand-in for passwords, hashes and tokens in `Debug` output.
+pub struct Hidden;
+
+impl fmt::Debug for Hidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<hidden>")
+    }
+}
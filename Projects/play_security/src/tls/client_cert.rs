@@ -0,0 +1,60 @@
+use axum::Extension;
+use axum::middleware::AddExtension;
+use axum_server::accept::{Accept, DefaultAcceptor};
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// Client certificate of the connection a request arrived on. rustls has
+/// already verified it against `tls.client_ca_paths` during the handshake.
+#[derive(Debug, Clone)]
+pub struct ClientCertificate {
+    /// `None` when the client did not present a certificate.
+    pub subject: Option<String>,
+}
+
+/// TLS acceptor that attaches the peer's `ClientCertificate` to every
+/// request on the connection.
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> ClientCertAcceptor {
+        ClientCertAcceptor {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, ClientCertificate>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor: RustlsAcceptor<DefaultAcceptor> = self.inner.clone();
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let subject = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|leaf| X509Certificate::from_der(leaf).ok())
+                .map(|(_, cert)| cert.subject().to_string());
+            let service = Extension(ClientCertificate { subject }).layer(service);
+            Ok((stream, service))
+        })
+    }
+}
@@ -0,0 +1,142 @@
+pub mod client_cert;
+pub mod redirect;
+
+use crate::config::TlsConfig;
+use crate::shutdown::Shutdown;
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::RootCertStore;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ServerConfig, WebPkiClientVerifier};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+const CERT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Loads the configured certificate, key and client CA bundles. Uses ring,
+/// the same crypto provider sqlx already pulls in for its rustls
+/// connections.
+pub fn rustls_config(tls: &TlsConfig) -> Result<RustlsConfig, String> {
+    // Fails only if a provider is already installed, which is fine.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    server_config(tls).map(|c| RustlsConfig::from_config(Arc::new(c)))
+}
+
+fn server_config(tls: &TlsConfig) -> Result<ServerConfig, String> {
+    let certs = load_certs(&tls.cert_path)?;
+    let key = load_key(&tls.key_path)?;
+
+    let builder = ServerConfig::builder();
+    let builder = if tls.client_ca_paths.is_empty() {
+        builder.with_no_client_auth()
+    } else {
+        let mut roots = RootCertStore::empty();
+        for path in &tls.client_ca_paths {
+            for ca in load_certs(path)? {
+                roots
+                    .add(ca)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+            }
+        }
+        let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+        // Without a required certificate, user callers keep using bearer
+        // tokens on the same listener.
+        let verifier = match tls.require_client_cert {
+            true => verifier,
+            false => verifier.allow_unauthenticated(),
+        };
+        builder.with_client_cert_verifier(verifier.build().map_err(|e| e.to_string())?)
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("{}: {e}", tls.cert_path.display()))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("{}: no PEM certificates found", path.display()));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("{}: {e}", path.display()))?
+        .ok_or_else(|| format!("{}: no PEM private key found", path.display()))
+}
+
+/// Swaps in a new certificate and client CA bundles on SIGHUP and whenever
+/// one of their files changes. New handshakes use them; open connections
+/// keep the old ones. Files that fail to load are logged and the current
+/// setup is kept.
+pub fn spawn_cert_reloader(rustls: RustlsConfig, tls: TlsConfig, shutdown: &Shutdown) {
+    shutdown.spawn(async move {
+        let mut last_modified = modified_all(&tls);
+        let mut poll = tokio::time::interval(CERT_POLL_INTERVAL);
+        #[cfg(unix)]
+        let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("TLS: SIGHUP certificate reload unavailable: {e}");
+                None
+            }
+        };
+
+        loop {
+            #[cfg(unix)]
+            let sighup = async {
+                match hangup.as_mut() {
+                    Some(s) => s.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            #[cfg(not(unix))]
+            let sighup = std::future::pending::<Option<()>>();
+
+            tokio::select! {
+                _ = sighup => reload(&rustls, &tls, "SIGHUP"),
+                _ = poll.tick() => {
+                    let current = modified_all(&tls);
+                    if current != last_modified {
+                        last_modified = current;
+                        reload(&rustls, &tls, "file change");
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn reload(rustls: &RustlsConfig, tls: &TlsConfig, trigger: &str) {
+    match server_config(tls) {
+        Ok(config) => {
+            rustls.reload_from_config(Arc::new(config));
+            info!("TLS: Certificate reloaded ({trigger})");
+        }
+        Err(e) => error!("TLS: Certificate reload ({trigger}) failed, keeping current one: {e}"),
+    }
+}
+
+fn modified_all(tls: &TlsConfig) -> Vec<Option<SystemTime>> {
+    [&tls.cert_path, &tls.key_path]
+        .into_iter()
+        .chain(&tls.client_ca_paths)
+        .map(|p| modified(p))
+        .collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
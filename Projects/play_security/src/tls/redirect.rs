@@ -0,0 +1,42 @@
+use crate::response::responses::Response;
+use axum::Router;
+use axum::extract::{Host, State};
+use axum::http::Uri;
+use axum::response::Redirect;
+
+/// Plain HTTP app that sends every request to the same host and path over
+/// HTTPS on `https_port`.
+pub fn redirect_route(https_port: u16) -> Router {
+    Router::new()
+        .fallback(redirect_to_https)
+        .with_state(https_port)
+}
+
+async fn redirect_to_https(
+    State(https_port): State<u16>,
+    Host(host): Host,
+    uri: Uri,
+) -> Result<Redirect, Response> {
+    let authority = match https_port {
+        443 => hostname(&host).to_string(),
+        port => format!("{}:{port}", hostname(&host)),
+    };
+    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+    let target = Uri::builder()
+        .scheme("https")
+        .authority(authority)
+        .path_and_query(path_and_query)
+        .build()
+        .map_err(|_| Response::BadRequest.with_detail("Invalid Host header"))?;
+    Ok(Redirect::permanent(&target.to_string()))
+}
+
+/// `Host` without its port, keeping the brackets of an IPv6 literal.
+fn hostname(host: &str) -> &str {
+    if host.starts_with('[') {
+        host.split_inclusive(']').next().unwrap_or(host)
+    } else {
+        host.split(':').next().unwrap_or(host)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::response::responses::Response;
+use axum::{
+    Json, async_trait,
+    extract::{FromRequest, Request, rejection::JsonRejection},
+};
+use regex::Regex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::sync::LazyLock;
+use tracing::error;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// Letters, digits, `.`, `_` and `-`, starting with a letter or digit.
+pub static USERNAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9._-]*$").unwrap());
+
+/// E.164: a leading `+`, no leading zero and at most 15 digits.
+pub static PHONE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\+[1-9][0-9]{1,14}$").unwrap());
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// Flattens `ValidationErrors` into one entry per failing rule, sorted by
+/// field so the response body is stable.
+pub fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut out: Vec<FieldError> = errors
+        .field_errors()
+        .into_iter()
+        .flat_map(|(field, errs)| {
+            errs.iter().map(move |e| FieldError {
+                field: field.to_string(),
+                code: e.code.to_string(),
+                message: e
+                    .message
+                    .as_ref()
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| format!("{field} is invalid")),
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| a.field.cmp(&b.field).then(a.code.cmp(&b.code)));
+    out
+}
+
+pub fn validate_ids(ids: &[i32]) -> Result<(), ValidationError> {
+    if ids.iter().any(|id| *id <= 0) {
+        return Err(ValidationError::new("invalid_id").with_message("ids must be positive".into()));
+    }
+    Ok(())
+}
+
+/// bcrypt only uses the first 72 bytes of a password, so anything longer
+/// would be silently truncated. `length(max)` counts characters, not bytes.
+pub fn validate_password_bytes(password: &str) -> Result<(), ValidationError> {
+    if password.len() > 72 {
+        return Err(
+            ValidationError::new("length").with_message("password must be at most 72 bytes".into())
+        );
+    }
+    Ok(())
+}
+
+/// `Json<T>` that also runs `T::validate` and rejects with a 422 listing
+/// every failing field.
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) =
+            Json::<T>::from_request(req, state)
+                .await
+                .map_err(|e: JsonRejection| {
+                    error!("VALIDATION: Malformed JSON body: {}", e.body_text());
+                    Response::from(e)
+                })?;
+
+        value.validate().map_err(|e| {
+            let errors = field_errors(&e);
+            error!(
+                "VALIDATION: Request rejected with {} field error(s)",
+                errors.len()
+            );
+            Response::ValidationFailed(errors)
+        })?;
+
+        Ok(ValidatedJson(value))
+    }
+}
@@ -0,0 +1,531 @@
+mod common;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode, header};
+use chrono::DateTime;
+use chrono::{Duration, SubsecRound, Utc};
+use common::{ADMIN, ADMIN_PASSWORD, Backend, JWT_SECRET, SESSION_HASH_KEY, TestApp, test_config};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+use play_security::auth::session_cache::SessionCache;
+use play_security::auth::token::{Token, session_hash};
+use play_security::config::Secret;
+use play_security::database::Database;
+use play_security::database::errors::DbError;
+use play_security::database::repository::{ActiveSession, SessionRepository};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+backend_tests!(
+    login_returns_token,
+    login_rejects_wrong_password,
+    login_rejects_unknown_user,
+    login_validates_body,
+    login_rejects_password_over_72_bytes,
+    login_is_rate_limited,
+    concurrent_logins_share_the_limit,
+    successful_logins_are_not_rate_limited,
+    token_grants_access,
+    missing_header_is_rejected,
+    non_bearer_scheme_is_rejected,
+    tampered_token_is_rejected,
+    token_from_other_secret_is_rejected,
+    new_login_revokes_previous_token,
+    previous_secret_still_verifies,
+    logout_revokes_token,
+    logout_bypasses_session_cache,
+    logout_requires_token,
+    session_stores_token_hash,
+    session_expiry_is_read_back_unchanged,
+    session_cache_drops_expired_sessions,
+    session_cache_is_not_filled_after_revoke,
+    token_carries_registered_claims,
+    token_for_other_audience_is_rejected,
+    token_from_other_issuer_is_rejected,
+    token_not_yet_valid_is_rejected,
+    token_within_leeway_is_accepted,
+    token_with_disallowed_algorithm_is_rejected,
+);
+
+async fn login_returns_token(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.login(ADMIN, ADMIN_PASSWORD).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json();
+    assert_eq!(body["code"], 200);
+    assert_eq!(body["data"]["username"], ADMIN);
+    assert!(body["data"].get("pwd").is_none());
+    assert!(!body["token"].as_str().unwrap().is_empty());
+}
+
+async fn login_rejects_wrong_password(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.login(ADMIN, "not-the-password").await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "invalid_credentials");
+}
+
+async fn login_rejects_unknown_user(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.login("nobody", ADMIN_PASSWORD).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "invalid_credentials");
+}
+
+async fn login_validates_body(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": "", "password": ADMIN_PASSWORD })),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.problem_code(), "validation_failed");
+    assert_eq!(response.json()["errors"][0]["field"], "username");
+}
+
+async fn login_rejects_password_over_72_bytes(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    // 40 characters, 80 bytes.
+    let password = "é".repeat(40);
+
+    let response = app.login(ADMIN, &password).await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.json()["errors"][0]["field"], "password");
+    assert_eq!(
+        response.json()["errors"][0]["message"],
+        "password must be at most 72 bytes"
+    );
+}
+
+async fn login_is_rate_limited(backend: Backend) {
+    let mut config = test_config(backend);
+    config.rate_limit.login_attempts_per_minute = 2;
+    let app = TestApp::with_config(config).await;
+
+    app.login(ADMIN, "wrong-1").await;
+    app.login(ADMIN, "wrong-2").await;
+    let response = app.login(ADMIN, ADMIN_PASSWORD).await;
+
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.problem_code(), "rate_limited");
+    assert!(response.headers.contains_key(header::RETRY_AFTER));
+}
+
+async fn concurrent_logins_share_the_limit(backend: Backend) {
+    let mut config = test_config(backend);
+    config.rate_limit.login_attempts_per_minute = 2;
+    let app = TestApp::with_config(config).await;
+
+    let responses = tokio::join!(
+        app.login(ADMIN, "wrong-1"),
+        app.login(ADMIN, "wrong-2"),
+        app.login(ADMIN, "wrong-3"),
+        app.login(ADMIN, "wrong-4"),
+    );
+    let responses = [responses.0, responses.1, responses.2, responses.3];
+
+    let limited = responses
+        .iter()
+        .filter(|r| r.status == StatusCode::TOO_MANY_REQUESTS)
+        .count();
+    assert_eq!(limited, 2);
+}
+
+async fn successful_logins_are_not_rate_limited(backend: Backend) {
+    let mut config = test_config(backend);
+    config.rate_limit.login_attempts_per_minute = 2;
+    let app = TestApp::with_config(config).await;
+
+    app.login(ADMIN, "wrong-1").await;
+    for _ in 0..3 {
+        let response = app.login(ADMIN, ADMIN_PASSWORD).await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+    // The successful logins cleared the earlier failure.
+    app.login(ADMIN, "wrong-2").await;
+    let response = app.login(ADMIN, ADMIN_PASSWORD).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+async fn token_grants_access(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+async fn missing_header_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.get("/users", None).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "unauthorized");
+}
+
+async fn non_bearer_scheme_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let request = Request::get("/users")
+        .header(header::AUTHORIZATION, format!("Token {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "unauthorized");
+}
+
+async fn tampered_token_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let mut tampered = token.into_bytes();
+    let last = tampered.len() - 2;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    let tampered = String::from_utf8(tampered).unwrap();
+    let response = app.get("/users", Some(&tampered)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "invalid_token");
+}
+
+async fn token_from_other_secret_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let mut config = test_config(backend);
+    config.auth.jwt_secret = Secret::new("another-secret-0123456789-abcdefgh");
+    let other = TestApp::with_config(config).await;
+    let token = other.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "invalid_token");
+}
+
+async fn new_login_revokes_previous_token(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let first = app.admin_token().await;
+    let second = app.admin_token().await;
+
+    assert_eq!(
+        app.get("/users", Some(&first)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.get("/users", Some(&second)).await.status,
+        StatusCode::OK
+    );
+}
+
+async fn previous_secret_still_verifies(backend: Backend) {
+    let mut config = test_config(backend);
+    config.auth.jwt_secret = Secret::new("rotated-secret-0123456789-abcdefgh");
+    config.auth.previous_jwt_secrets = vec![Secret::new(JWT_SECRET)];
+    let rotated = TestApp::with_config(config).await;
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    // The signature checks out against the previous secret; the session
+    // lives in the other app, so the lookup is what rejects it.
+    let response = rotated.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "Session not found or expired");
+}
+
+async fn logout_revokes_token(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app
+        .request(Method::POST, "/logout", Some(&token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+
+    let response = app.get("/users", Some(&token)).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "Session not found or expired");
+}
+
+async fn logout_bypasses_session_cache(backend: Backend) {
+    let mut config = test_config(backend);
+    config.session_cache.ttl_secs = 300;
+    let app = TestApp::with_config(config).await;
+    let token = app.admin_token().await;
+    // The first request caches the session, the second is served from it.
+    app.get("/users", Some(&token)).await;
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+
+    app.request(Method::POST, "/logout", Some(&token), None)
+        .await;
+
+    assert_eq!(
+        app.get("/users", Some(&token)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+async fn logout_requires_token(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.request(Method::POST, "/logout", None, None).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.problem_code(), "unauthorized");
+}
+
+async fn session_stores_token_hash(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let sessions = app.database.sessions();
+
+    assert_eq!(sessions.find_active(ADMIN, &token).await.unwrap(), None);
+    let hash = session_hash(&token, SESSION_HASH_KEY);
+    let session = sessions.find_active(ADMIN, &hash).await.unwrap().unwrap();
+    assert_eq!(session.username, ADMIN);
+}
+
+/// The expiry must not shift with the database server's time zone, or the
+/// session cache would trust expired sessions.
+async fn session_expiry_is_read_back_unchanged(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let sessions = app.database.sessions();
+    let now = Utc::now().trunc_subsecs(0);
+    let expires_at = now + Duration::hours(1);
+    sessions.save(ADMIN, "hash", now, expires_at).await.unwrap();
+
+    let session = sessions.find_user("hash").await.unwrap().unwrap();
+
+    assert_eq!(session.expires_at, expires_at);
+}
+
+async fn session_cache_drops_expired_sessions(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let cache = SessionCache::new(app.database.sessions(), &test_config(backend).session_cache);
+    let now = Utc::now();
+    cache
+        .save(ADMIN, "hash", now, now + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert!(cache.find_user("hash").await.unwrap().is_some());
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    assert_eq!(cache.find_user("hash").await.unwrap(), None);
+}
+
+/// Holds `find_user` after the lookup, so a revoke can run before the
+/// cache gets the result.
+struct PausedLookup {
+    inner: Arc<dyn SessionRepository>,
+    looked_up: Notify,
+    resume: Notify,
+}
+
+#[async_trait]
+impl SessionRepository for PausedLookup {
+    async fn save(
+        &self,
+        username: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DbError> {
+        self.inner
+            .save(username, token_hash, created_at, expires_at)
+            .await
+    }
+
+    async fn find_active(
+        &self,
+        username: &str,
+        token_hash: &str,
+    ) -> Result<Option<ActiveSession>, DbError> {
+        self.inner.find_active(username, token_hash).await
+    }
+
+    async fn find_user(&self, token_hash: &str) -> Result<Option<ActiveSession>, DbError> {
+        let found = self.inner.find_user(token_hash).await;
+        self.looked_up.notify_one();
+        self.resume.notified().await;
+        found
+    }
+
+    async fn revoke(&self, usernames: &[String]) -> Result<(), DbError> {
+        self.inner.revoke(usernames).await
+    }
+}
+
+async fn session_cache_is_not_filled_after_revoke(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let paused = Arc::new(PausedLookup {
+        inner: app.database.sessions(),
+        looked_up: Notify::new(),
+        resume: Notify::new(),
+    });
+    let cache = Arc::new(SessionCache::new(
+        paused.clone(),
+        &test_config(backend).session_cache,
+    ));
+    let now = Utc::now();
+    cache
+        .save(ADMIN, "hash", now, now + Duration::hours(1))
+        .await
+        .unwrap();
+
+    let lookup = tokio::spawn({
+        let cache = cache.clone();
+        async move { cache.find_user("hash").await.unwrap() }
+    });
+    paused.looked_up.notified().await;
+    cache.revoke(&[ADMIN.to_string()]).await.unwrap();
+    paused.resume.notify_one();
+    // The lookup read the session before it was revoked.
+    assert!(lookup.await.unwrap().is_some());
+
+    // Lets the next lookup through, if it reaches the repository at all.
+    paused.resume.notify_one();
+    assert_eq!(cache.find_user("hash").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn login_fails_when_session_is_not_saved() {
+    let app = TestApp::new(Backend::Sqlite).await;
+    let Database::Sqlite(pool) = &app.database else {
+        unreachable!()
+    };
+    sqlx::query("DROP TABLE user_login")
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let response = app.login(ADMIN, ADMIN_PASSWORD).await;
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.json().get("token").is_none());
+}
+
+/// Signs admin claims with the test secret and stores the session, so only
+/// the claims decide whether the token is accepted.
+async fn signed_token(app: &TestApp, algorithm: Algorithm, not_before: Duration) -> String {
+    let auth = test_config(Backend::Memory).auth;
+    let now = Utc::now();
+    let claims = Token {
+        sub: ADMIN.to_string(),
+        iss: auth.issuer,
+        aud: auth.audience,
+        exp: (now + Duration::hours(1)).timestamp() as usize,
+        nbf: (now + not_before).timestamp() as usize,
+        iat: now.timestamp() as usize,
+        jti: "test-token".to_string(),
+    };
+    let token = encode(
+        &Header::new(algorithm),
+        &claims,
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .unwrap();
+    app.database
+        .sessions()
+        .save(
+            ADMIN,
+            &session_hash(&token, SESSION_HASH_KEY),
+            now,
+            now + Duration::hours(1),
+        )
+        .await
+        .unwrap();
+    token
+}
+
+async fn token_carries_registered_claims(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let config = test_config(backend);
+    let first = app.admin_token().await;
+    let second = app.admin_token().await;
+
+    let issuer = config.auth.token_issuer();
+    let claims = issuer.verify(&first, &config.auth).unwrap().unwrap();
+    let now = Utc::now().timestamp() as usize;
+    assert_eq!(claims.sub, ADMIN);
+    assert_eq!(claims.iss, config.auth.issuer);
+    assert_eq!(claims.aud, config.auth.audience);
+    assert!(claims.nbf <= now && now < claims.exp);
+    assert_eq!(claims.exp - claims.iat, config.auth.token_ttl_secs as usize);
+    let other = issuer.verify(&second, &config.auth).unwrap().unwrap();
+    assert_ne!(claims.jti, other.jti);
+}
+
+async fn token_for_other_audience_is_rejected(backend: Backend) {
+    let mut config = test_config(backend);
+    config.auth.audience = "another-service".to_string();
+    let other = TestApp::with_config(config).await;
+    let app = TestApp::new(backend).await;
+    let token = other.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "InvalidAudience");
+}
+
+async fn token_from_other_issuer_is_rejected(backend: Backend) {
+    let mut config = test_config(backend);
+    config.auth.issuer = "another-issuer".to_string();
+    let other = TestApp::with_config(config).await;
+    let app = TestApp::new(backend).await;
+    let token = other.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "InvalidIssuer");
+}
+
+async fn token_not_yet_valid_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = signed_token(&app, Algorithm::HS256, Duration::minutes(5)).await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "ImmatureSignature");
+}
+
+async fn token_within_leeway_is_accepted(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = signed_token(&app, Algorithm::HS256, Duration::seconds(10)).await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+}
+
+async fn token_with_disallowed_algorithm_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = signed_token(&app, Algorithm::HS512, Duration::zero()).await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "InvalidAlgorithm");
+}
@@ -0,0 +1,303 @@
+//! Builds the full router with test configuration and drives it with
+//! in-process requests. Only the Postgres suites need a server, named by
+//! `TEST_DATABASE_URL`; they are skipped when it is unset.
+#![allow(dead_code)]
+
+use axum::Router;
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderMap, Method, Request, StatusCode, header};
+use play_security::auth::rate_limit::LoginRateLimiter;
+use play_security::auth::session_cache::SessionCache;
+use play_security::config::{Config, Secret, SharedConfig};
+use play_security::database::bootstrap::bootstrap_admin;
+use play_security::database::{Database, MEMORY_URL};
+use play_security::state::AppState;
+use serde_json::{Value, json};
+use sqlx::{Connection, Executor, PgConnection};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+pub const ADMIN: &str = "admin";
+pub const ADMIN_PASSWORD: &str = "integration-admin-password";
+pub const JWT_SECRET: &str = "integration-test-secret-0123456789";
+pub const SESSION_HASH_KEY: &str = "integration-session-key-0123456789";
+
+/// Server the Postgres suites run against, e.g.
+/// `postgres://postgres@localhost:5432/postgres`. Each `TestApp` gets its
+/// own database on it, dropped with the app, so the user needs `CREATEDB`.
+pub const TEST_DATABASE_URL: &str = "TEST_DATABASE_URL";
+
+/// Every suite runs once per backend; see `backend_tests!`.
+#[derive(Clone, Copy, Debug)]
+pub enum Backend {
+    Memory,
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    fn url(self) -> String {
+        match self {
+            Backend::Memory => MEMORY_URL.to_string(),
+            Backend::Sqlite => "sqlite::memory:".to_string(),
+            Backend::Postgres => std::env::var(TEST_DATABASE_URL).unwrap_or_default(),
+        }
+    }
+
+    /// Whether this backend can run here; only Postgres needs a server.
+    pub fn available(self) -> bool {
+        !self.url().is_empty()
+    }
+}
+
+/// Expands each listed `async fn name(backend: Backend)` into a
+/// `#[tokio::test]` per backend, as `memory::name`, `sqlite::name` and
+/// `postgres::name`.
+#[macro_export]
+macro_rules! backend_tests {
+    ($($name:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name($crate::common::Backend::Memory).await;
+                }
+            )*
+        }
+
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name($crate::common::Backend::Sqlite).await;
+                }
+            )*
+        }
+
+        mod postgres {
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    if !$crate::common::Backend::Postgres.available() {
+                        eprintln!("{} is not set, skipping", $crate::common::TEST_DATABASE_URL);
+                        return;
+                    }
+                    super::$name($crate::common::Backend::Postgres).await;
+                }
+            )*
+        }
+    };
+}
+
+pub fn test_config(backend: Backend) -> Config {
+    let mut config = Config::default();
+    config.database.url = Secret::new(backend.url());
+    config.auth.jwt_secret = Secret::new(JWT_SECRET);
+    config.auth.session_hash_key = Secret::new(SESSION_HASH_KEY);
+    config.admin.username = ADMIN.to_string();
+    config.admin.phone = "+27820000001".to_string();
+    config.admin.email = "admin@example.com".to_string();
+    config.admin.password = Some(Secret::new(ADMIN_PASSWORD));
+    config
+}
+
+pub struct TestApp {
+    router: Router,
+    pub database: Database,
+    /// The per-app Postgres database, dropped with the app.
+    postgres_database: Option<String>,
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| {
+            panic!(
+                "response is not JSON ({e}): {}",
+                String::from_utf8_lossy(&self.body)
+            )
+        })
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// The `code` of an `application/problem+json` body.
+    pub fn problem_code(&self) -> String {
+        assert_eq!(
+            self.headers.get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        self.json()["code"].as_str().unwrap_or_default().to_string()
+    }
+}
+
+impl TestApp {
+    pub async fn new(backend: Backend) -> TestApp {
+        TestApp::with_config(test_config(backend)).await
+    }
+
+    /// Starts the app the way `main` does: connect, migrate, bootstrap the
+    /// configured admin, wrap sessions in the cache, then build the router.
+    pub async fn with_config(mut config: Config) -> TestApp {
+        let postgres_database = match config.database.url.expose().starts_with("postgres") {
+            true => Some(create_postgres_database(&mut config).await),
+            false => None,
+        };
+        let database = Database::connect(&config)
+            .await
+            .expect("failed to open test database");
+        database.migrate().await.expect("failed to migrate");
+        bootstrap_admin(database.users().as_ref(), config.admin.account(), false)
+            .await
+            .expect("failed to bootstrap admin");
+
+        let mut sessions = database.sessions();
+        if config.session_cache.ttl_secs > 0 {
+            sessions = Arc::new(SessionCache::new(sessions, &config.session_cache));
+        }
+        let state = AppState {
+            users: database.users(),
+            sessions,
+            readiness: database.readiness(),
+            config: SharedConfig::new(config),
+            login_limiter: Arc::new(LoginRateLimiter::default()),
+        };
+        TestApp {
+            router: play_security::app(state, None, None),
+            database,
+            postgres_database,
+        }
+    }
+
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> TestResponse {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let body = match body {
+            Some(json) => {
+                builder = builder.header(header::CONTENT_TYPE, "application/json");
+                Body::from(json.to_string())
+            }
+            None => Body::empty(),
+        };
+        self.send(builder.body(body).unwrap()).await
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec();
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    pub async fn get(&self, uri: &str, token: Option<&str>) -> TestResponse {
+        self.request(Method::GET, uri, token, None).await
+    }
+
+    pub async fn login(&self, username: &str, password: &str) -> TestResponse {
+        self.request(
+            Method::POST,
+            "/login",
+            None,
+            Some(json!({ "username": username, "password": password })),
+        )
+        .await
+    }
+
+    pub async fn admin_token(&self) -> String {
+        self.token(ADMIN, ADMIN_PASSWORD).await
+    }
+
+    /// Logs in and returns the token, failing the test if login fails.
+    pub async fn token(&self, username: &str, password: &str) -> String {
+        let response = self.login(username, password).await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+        response.json()["token"].as_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let Some(name) = self.postgres_database.take() else {
+            return;
+        };
+        // Drop cannot await, so the cleanup gets a runtime of its own.
+        let dropped = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    let mut conn = PgConnection::connect(&Backend::Postgres.url()).await?;
+                    conn.execute(
+                        format!(r#"DROP DATABASE IF EXISTS "{name}" WITH (FORCE)"#).as_str(),
+                    )
+                    .await
+                    .map(|_| ())
+                })
+        })
+        .join();
+        if let Ok(Err(e)) = dropped {
+            eprintln!("failed to drop test database: {e}");
+        }
+    }
+}
+
+/// Creates an empty database on the `TEST_DATABASE_URL` server and points
+/// `config` at it, so concurrent tests do not share tables.
+async fn create_postgres_database(config: &mut Config) -> String {
+    let server = config.database.url.expose().to_string();
+    let name = format!("play_security_test_{:016x}", rand::random::<u64>());
+    let mut conn = PgConnection::connect(&server)
+        .await
+        .expect("failed to connect to TEST_DATABASE_URL");
+    conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str())
+        .await
+        .expect("failed to create test database");
+
+    let (base, query) = match server.split_once('?') {
+        Some((base, query)) => (base, format!("?{query}")),
+        None => (server.as_str(), String::new()),
+    };
+    let path_start = base.find("://").map_or(0, |i| i + 3);
+    let authority = match base[path_start..].find('/') {
+        Some(i) => &base[..path_start + i],
+        None => base,
+    };
+    config.database.url = Secret::new(format!("{authority}/{name}{query}"));
+    name
+}
+
+/// A valid `POST /users` body; `n` keeps the unique fields apart.
+pub fn new_user(n: u32) -> Value {
+    json!({
+        "username": format!("user{n}"),
+        "name": "Test",
+        "surname": "User",
+        "phone": format!("+2782100{n:04}"),
+        "email": format!("user{n}@example.com"),
+        "created_by": ADMIN,
+    })
+}
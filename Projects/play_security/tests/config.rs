@@ -0,0 +1,506 @@
+//! `Config::from_sources` and `SharedConfig::reload` read the process
+//! environment, so every test holds `ENV_LOCK` and starts from a clean slate
+//! through `TestEnv`.
+
+use play_security::config::reload::LogLevelHandle;
+use play_security::config::{CONFIG_PATH_ENV, Config, Environment, SharedConfig};
+use play_security::telemetry::redact::PiiPolicy;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Every variable `Config::from_sources` reads.
+const CONFIG_VARS: &[&str] = &[
+    "APP_ENV",
+    "BIND_ADDR",
+    "SHUTDOWN_TIMEOUT_SECS",
+    "DATABASE_URL",
+    "DB_MAX_CONNECTIONS",
+    "DB_MIN_CONNECTIONS",
+    "DB_CONNECT_TIMEOUT",
+    "DB_IDLE_TIMEOUT",
+    "TOKEN_FORMAT",
+    "JWT_SECRET",
+    "JWT_PREVIOUS_SECRETS",
+    "PASETO_KEY",
+    "PASETO_PREVIOUS_KEYS",
+    "SESSION_HASH_KEY",
+    "TOKEN_TTL_SECS",
+    "JWT_ISSUER",
+    "JWT_AUDIENCE",
+    "JWT_LEEWAY_SECS",
+    "JWT_ALGORITHMS",
+    "ADMIN_USERNAME",
+    "ADMIN_NAME",
+    "ADMIN_SURNAME",
+    "ADMIN_PHONE",
+    "ADMIN_EMAIL",
+    "ADMIN_PASSWORD",
+    "LOG_LEVEL",
+    "LOG_FORMAT",
+    "LOG_PII",
+    "CORS_ALLOWED_ORIGINS",
+    "LOGIN_ATTEMPTS_PER_MINUTE",
+    "SESSION_CACHE_TTL_SECS",
+    "SESSION_CACHE_MAX_ENTRIES",
+    "TLS_ENABLED",
+    "TLS_CERT_PATH",
+    "TLS_KEY_PATH",
+    "TLS_REDIRECT_HTTP_ADDR",
+    "TLS_CLIENT_CA_PATHS",
+    "TLS_REQUIRE_CLIENT_CERT",
+    "METRICS_ENABLED",
+    "METRICS_ADMIN_ADDR",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "TRACING_FILE_PATH",
+    "OTEL_SERVICE_NAME",
+];
+
+const JWT_SECRET: &str = "config-test-jwt-secret-0123456789";
+const SESSION_HASH_KEY: &str = "config-test-session-key-0123456789";
+const PASETO_KEY: &str = "707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f";
+
+/// A config that passes validation; tests append to or override it.
+fn valid_toml() -> String {
+    format!(
+        r#"
+[database]
+url = "memory://"
+
+[auth]
+jwt_secret = "{JWT_SECRET}"
+session_hash_key = "{SESSION_HASH_KEY}"
+"#
+    )
+}
+
+/// Holds `ENV_LOCK` with every config variable cleared and
+/// `PLAY_SECURITY_CONFIG` pointing at a file of its own. The variables and
+/// files are removed again on drop.
+struct TestEnv {
+    config_path: PathBuf,
+    dotenv_path: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    fn new(toml: &str) -> TestEnv {
+        let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let id = rand::random::<u64>();
+        let dir = std::env::temp_dir();
+        let env = TestEnv {
+            config_path: dir.join(format!("play_security_config_{id:016x}.toml")),
+            dotenv_path: dir.join(format!("play_security_dotenv_{id:016x}")),
+            _lock: lock,
+        };
+        clear_vars();
+        env.write_toml(toml);
+        set_var(CONFIG_PATH_ENV, env.config_path.to_str().unwrap());
+        env
+    }
+
+    fn write_toml(&self, toml: &str) {
+        std::fs::write(&self.config_path, toml).unwrap();
+    }
+
+    /// Loads `contents` the way `Config::load` loads `.env`.
+    fn load_dotenv(&self, contents: &str) {
+        std::fs::write(&self.dotenv_path, contents).unwrap();
+        dotenv::from_path(&self.dotenv_path).unwrap();
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        clear_vars();
+        remove_var(CONFIG_PATH_ENV);
+        let _ = std::fs::remove_file(&self.config_path);
+        let _ = std::fs::remove_file(&self.dotenv_path);
+    }
+}
+
+fn clear_vars() {
+    for key in CONFIG_VARS {
+        remove_var(key);
+    }
+}
+
+fn set_var(key: &str, value: &str) {
+    // SAFETY: only called while holding `ENV_LOCK`, and nothing else in this
+    // test binary reads the environment concurrently.
+    unsafe { std::env::set_var(key, value) }
+}
+
+fn remove_var(key: &str) {
+    // SAFETY: as in `set_var`.
+    unsafe { std::env::remove_var(key) }
+}
+
+/// The problems `from_sources` reports, failing the test if it succeeds.
+fn rejections() -> Vec<String> {
+    match Config::from_sources() {
+        Ok(config) => panic!("config was accepted: {config:?}"),
+        Err(e) => e.0,
+    }
+}
+
+fn assert_rejected(expected: &str) {
+    let errors = rejections();
+    assert!(
+        errors.iter().any(|e| e == expected),
+        "expected {expected:?} in {errors:#?}"
+    );
+}
+
+/// A handle `SharedConfig::reload` can apply log levels through. The layer
+/// has to outlive the handle for a reload to succeed.
+fn log_level_handle() -> (reload::Layer<EnvFilter, Registry>, LogLevelHandle) {
+    reload::Layer::new(EnvFilter::new("info"))
+}
+
+#[test]
+fn defaults_apply_without_overrides() {
+    let _env = TestEnv::new(&valid_toml());
+
+    let config = Config::from_sources().unwrap();
+
+    assert_eq!(config.environment, Environment::Development);
+    assert_eq!(config.server.bind_addr.to_string(), "127.0.0.1:3000");
+    assert_eq!(config.database.url.expose(), "memory://");
+    assert_eq!(config.auth.jwt_secret.expose(), JWT_SECRET);
+    assert_eq!(config.auth.issuer, "play_security");
+    assert_eq!(config.log.level, "info");
+}
+
+#[test]
+fn environment_overrides_toml() {
+    let _env = TestEnv::new(&format!(
+        "[server]\nbind_addr = \"127.0.0.1:4000\"\nshutdown_timeout_secs = 5\n{}\n[log]\nlevel = \"warn\"\n",
+        valid_toml()
+    ));
+    set_var("BIND_ADDR", "127.0.0.1:5000");
+    set_var("LOG_LEVEL", "debug");
+
+    let config = Config::from_sources().unwrap();
+
+    assert_eq!(config.server.bind_addr.to_string(), "127.0.0.1:5000");
+    assert_eq!(config.server.shutdown_timeout_secs, 5);
+    assert_eq!(config.log.level, "debug");
+}
+
+#[test]
+fn dotenv_overrides_toml_but_not_environment() {
+    let env = TestEnv::new(&format!("{}\n[log]\nlevel = \"warn\"\n", valid_toml()));
+    set_var("JWT_ISSUER", "from-environment");
+    env.load_dotenv("LOG_LEVEL=debug\nJWT_ISSUER=from-dotenv\n");
+
+    let config = Config::from_sources().unwrap();
+
+    assert_eq!(config.log.level, "debug");
+    assert_eq!(config.auth.issuer, "from-environment");
+}
+
+#[test]
+fn list_variables_are_comma_separated() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var(
+        "CORS_ALLOWED_ORIGINS",
+        "https://a.example.com, https://b.example.com,",
+    );
+    set_var("JWT_ALGORITHMS", "HS512,HS256");
+
+    let config = Config::from_sources().unwrap();
+
+    assert_eq!(
+        config.cors.allowed_origins,
+        ["https://a.example.com", "https://b.example.com"]
+    );
+    assert_eq!(
+        config.auth.algorithms,
+        [
+            jsonwebtoken::Algorithm::HS512,
+            jsonwebtoken::Algorithm::HS256
+        ]
+    );
+}
+
+#[test]
+fn every_problem_is_reported_together() {
+    let _env = TestEnv::new("");
+    set_var("APP_ENV", "staging");
+    set_var("DB_MAX_CONNECTIONS", "many");
+
+    let errors = rejections();
+
+    assert!(errors.contains(&"database.url (DATABASE_URL) is required".to_string()));
+    assert!(errors.contains(&"auth.jwt_secret (JWT_SECRET) is required".to_string()));
+    assert!(
+        errors.contains(
+            &"APP_ENV: expected 'development' or 'production', got 'staging'".to_string()
+        )
+    );
+    assert!(errors.iter().any(|e| e.starts_with("DB_MAX_CONNECTIONS: ")));
+}
+
+#[test]
+fn unreadable_toml_is_rejected() {
+    let env = TestEnv::new("[server\n");
+
+    let errors = rejections();
+
+    let path = env.config_path.display().to_string();
+    assert!(errors.iter().any(|e| e.starts_with(&path)), "{errors:#?}");
+}
+
+#[test]
+fn unknown_toml_key_is_rejected() {
+    let _env = TestEnv::new(&format!("bind = \"0.0.0.0:80\"\n{}", valid_toml()));
+
+    let errors = rejections();
+
+    assert!(
+        errors.iter().any(|e| e.contains("unknown field `bind`")),
+        "{errors:#?}"
+    );
+}
+
+#[test]
+fn unsupported_database_url_is_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("DATABASE_URL", "mysql://localhost/app");
+
+    assert_rejected(
+        "database.url (DATABASE_URL) must be a postgres:// or sqlite: URL, or memory://",
+    );
+}
+
+#[test]
+fn production_rejects_memory_database() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("APP_ENV", "production");
+    set_var("LOG_PII", "redact");
+
+    assert_rejected("database.url (DATABASE_URL) memory:// is not allowed in production");
+}
+
+#[test]
+fn production_requires_pii_redaction() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("APP_ENV", "production");
+    set_var("DATABASE_URL", "sqlite://app.db");
+
+    for policy in ["partial", "plain"] {
+        set_var("LOG_PII", policy);
+        assert_rejected("log.pii (LOG_PII) must be 'redact' in production");
+    }
+
+    // Debug builds default to `partial`, so production has to say `redact`.
+    remove_var("LOG_PII");
+    if cfg!(debug_assertions) {
+        assert_rejected("log.pii (LOG_PII) must be 'redact' in production");
+    } else {
+        assert_eq!(Config::from_sources().unwrap().log.pii, PiiPolicy::Redact);
+    }
+}
+
+#[test]
+fn production_accepts_a_complete_config() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("APP_ENV", "production");
+    set_var("DATABASE_URL", "sqlite://app.db");
+    set_var("LOG_PII", "redact");
+
+    let config = Config::from_sources().unwrap();
+
+    assert!(config.is_production());
+    assert_eq!(config.log.pii, PiiPolicy::Redact);
+}
+
+#[test]
+fn short_jwt_secret_is_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("JWT_SECRET", "too-short");
+
+    assert_rejected("auth.jwt_secret (JWT_SECRET) must be at least 32 bytes");
+}
+
+#[test]
+fn short_previous_jwt_secret_is_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("JWT_PREVIOUS_SECRETS", "too-short");
+
+    assert_rejected("auth.previous_jwt_secrets entries must be at least 32 bytes");
+}
+
+#[test]
+fn session_hash_key_is_required() {
+    let _env = TestEnv::new("[database]\nurl = \"memory://\"\n");
+    set_var("JWT_SECRET", JWT_SECRET);
+
+    assert_rejected("auth.session_hash_key (SESSION_HASH_KEY) is required");
+}
+
+#[test]
+fn short_session_hash_key_is_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("SESSION_HASH_KEY", "too-short");
+
+    assert_rejected("auth.session_hash_key (SESSION_HASH_KEY) must be at least 32 bytes");
+}
+
+#[test]
+fn paseto_formats_require_a_key() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("TOKEN_FORMAT", "paseto_v4_local");
+
+    assert_rejected("auth.paseto_key (PASETO_KEY) is required for the paseto token formats");
+}
+
+#[test]
+fn paseto_keys_must_be_64_hex_characters() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("TOKEN_FORMAT", "paseto_v4_public");
+    set_var("PASETO_KEY", PASETO_KEY);
+
+    assert!(Config::from_sources().is_ok());
+
+    for bad in ["abcd", &PASETO_KEY.replace('7', "g")] {
+        set_var("PASETO_KEY", bad);
+        assert_rejected(
+            "auth.paseto_key and auth.previous_paseto_keys must be 64 hex characters (32 bytes)",
+        );
+    }
+
+    set_var("PASETO_KEY", PASETO_KEY);
+    set_var("PASETO_PREVIOUS_KEYS", "abcd");
+    assert_rejected(
+        "auth.paseto_key and auth.previous_paseto_keys must be 64 hex characters (32 bytes)",
+    );
+}
+
+#[test]
+fn asymmetric_jwt_algorithms_are_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("JWT_ALGORITHMS", "RS256");
+
+    assert_rejected(
+        "auth.algorithms (JWT_ALGORITHMS): RS256 is not supported, tokens are signed with shared secrets (HS256, HS384, HS512)",
+    );
+}
+
+#[test]
+fn session_cache_ttl_is_capped() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("SESSION_CACHE_TTL_SECS", "301");
+
+    assert_rejected("session_cache.ttl_secs must be at most 300 seconds");
+
+    set_var("SESSION_CACHE_TTL_SECS", "300");
+    assert_eq!(Config::from_sources().unwrap().session_cache.ttl_secs, 300);
+}
+
+#[test]
+fn invalid_log_level_is_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("LOG_LEVEL", "play_security=loud");
+
+    let errors = rejections();
+
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.starts_with("log.level 'play_security=loud' is not a valid filter")),
+        "{errors:#?}"
+    );
+}
+
+#[test]
+fn cors_origin_with_path_is_rejected() {
+    let _env = TestEnv::new(&valid_toml());
+    set_var("CORS_ALLOWED_ORIGINS", "https://app.example.com/");
+
+    assert_rejected(
+        "cors.allowed_origins entry 'https://app.example.com/' must look like https://host[:port]",
+    );
+}
+
+#[test]
+fn reload_applies_runtime_changes() {
+    let env = TestEnv::new(&valid_toml());
+    let shared = SharedConfig::new(Config::from_sources().unwrap());
+    let (_layer, log_level) = log_level_handle();
+
+    env.write_toml(&format!(
+        "{}\n[log]\nlevel = \"debug\"\n[cors]\nallowed_origins = [\"https://app.example.com\"]\n[rate_limit]\nlogin_attempts_per_minute = 5\n",
+        valid_toml()
+    ));
+    shared.reload(&log_level, "test");
+
+    let config = shared.load();
+    assert_eq!(config.log.level, "debug");
+    assert_eq!(log_level.with_current(|f| f.to_string()).unwrap(), "debug");
+    assert_eq!(config.cors.allowed_origins, ["https://app.example.com"]);
+    assert_eq!(config.rate_limit.login_attempts_per_minute, 5);
+}
+
+#[test]
+fn reload_rejects_startup_only_changes() {
+    let env = TestEnv::new(&valid_toml());
+    let original = Config::from_sources().unwrap();
+    let shared = SharedConfig::new(original.clone());
+    let (_layer, log_level) = log_level_handle();
+
+    for startup_only in [
+        "[server]\nbind_addr = \"127.0.0.1:4000\"\n",
+        "[database]\nmax_connections = 9\n",
+        "[tls]\ncert_path = \"/etc/play_security/cert.pem\"\n",
+    ] {
+        // The runtime change rides along and must not be applied either.
+        env.write_toml(&format!(
+            "{}\n[log]\nlevel = \"debug\"\n",
+            merge_toml(&valid_toml(), startup_only)
+        ));
+        shared.reload(&log_level, "test");
+
+        assert_eq!(*shared.load(), original, "applied {startup_only:?}");
+        assert_eq!(log_level.with_current(|f| f.to_string()).unwrap(), "info");
+    }
+}
+
+#[test]
+fn reload_keeps_config_when_file_is_invalid() {
+    let env = TestEnv::new(&valid_toml());
+    let original = Config::from_sources().unwrap();
+    let shared = SharedConfig::new(original.clone());
+    let (_layer, log_level) = log_level_handle();
+
+    env.write_toml("[log]\nlevel = \"debug\"\nunknown = true\n");
+    shared.reload(&log_level, "test");
+    assert_eq!(*shared.load(), original);
+
+    env.write_toml(&format!("{}\n[log]\nlevel = \"debug\"\n", valid_toml()));
+    set_var("SESSION_HASH_KEY", "too-short");
+    shared.reload(&log_level, "test");
+    assert_eq!(*shared.load(), original);
+    assert_eq!(log_level.with_current(|f| f.to_string()).unwrap(), "info");
+}
+
+/// `valid_toml` with the tables in `extra` merged in, so a test can set keys
+/// in `[database]` without declaring the table twice.
+fn merge_toml(base: &str, extra: &str) -> String {
+    let mut base: toml::Table = toml::from_str(base).unwrap();
+    let extra: toml::Table = toml::from_str(extra).unwrap();
+    for (section, values) in extra {
+        match (base.get_mut(&section), values) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(values)) => {
+                existing.extend(values)
+            }
+            (_, values) => {
+                base.insert(section, values);
+            }
+        }
+    }
+    toml::to_string(&base).unwrap()
+}
@@ -0,0 +1,123 @@
+//! The PII policy is process-wide, so every test holds `POLICY_LOCK` while
+//! it sets one and formats records.
+
+use play_security::database::bootstrap::AdminAccount;
+use play_security::routes::users::UserData;
+use play_security::telemetry::redact::{PiiPolicy, set_policy};
+use serde_json::json;
+use std::sync::{Mutex, MutexGuard};
+
+static POLICY_LOCK: Mutex<()> = Mutex::new(());
+
+const NAME: &str = "Johanna";
+const SURNAME: &str = "Smit";
+const PHONE: &str = "+27821234578";
+const EMAIL: &str = "johanna@example.com";
+const PASSWORD: &str = "correct-horse-battery";
+
+fn with_policy(policy: PiiPolicy) -> MutexGuard<'static, ()> {
+    let lock = POLICY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    set_policy(policy);
+    lock
+}
+
+fn user() -> UserData {
+    serde_json::from_value(json!({
+        "username": "user42",
+        "name": NAME,
+        "surname": SURNAME,
+        "phone": PHONE,
+        "email": EMAIL,
+        "created_by": "admin",
+    }))
+    .unwrap()
+}
+
+fn admin() -> AdminAccount {
+    AdminAccount {
+        username: "root".to_string(),
+        name: NAME.to_string(),
+        surname: SURNAME.to_string(),
+        phone: PHONE.to_string(),
+        email: EMAIL.to_string(),
+        password: PASSWORD.to_string(),
+    }
+}
+
+/// The `Debug` output of both records under the active policy.
+fn formatted() -> [String; 2] {
+    [format!("{:?}", user()), format!("{:?}", admin())]
+}
+
+#[test]
+fn redact_hides_personal_data() {
+    let _policy = with_policy(PiiPolicy::Redact);
+
+    for output in formatted() {
+        for value in [NAME, SURNAME, PHONE, EMAIL, "example.com", PASSWORD] {
+            assert!(!output.contains(value), "{value} shown in {output}");
+        }
+        assert!(
+            output.contains(
+                r#"name: <redacted>, surname: <redacted>, phone: <redacted>, email: <redacted>"#
+            ),
+            "{output}"
+        );
+    }
+}
+
+#[test]
+fn partial_keeps_enough_to_tell_records_apart() {
+    let _policy = with_policy(PiiPolicy::Partial);
+
+    for output in formatted() {
+        for value in [NAME, SURNAME, PHONE, EMAIL, PASSWORD] {
+            assert!(!output.contains(value), "{value} shown in {output}");
+        }
+        assert!(
+            output.contains(
+                r#"name: "J***", surname: "S***", phone: "+27*******78", email: "j***@example.com""#
+            ),
+            "{output}"
+        );
+    }
+}
+
+#[test]
+fn plain_shows_personal_data() {
+    let _policy = with_policy(PiiPolicy::Plain);
+
+    for output in formatted() {
+        assert!(
+            output.contains(&format!(
+                r#"name: "{NAME}", surname: "{SURNAME}", phone: "{PHONE}", email: "{EMAIL}""#
+            )),
+            "{output}"
+        );
+    }
+}
+
+#[test]
+fn usernames_stay_readable() {
+    let _policy = with_policy(PiiPolicy::Redact);
+
+    let [user, admin] = formatted();
+
+    assert!(user.contains(r#"username: "user42""#), "{user}");
+    assert!(admin.contains(r#"username: "root""#), "{admin}");
+}
+
+#[test]
+fn passwords_are_hidden_under_every_policy() {
+    for policy in [PiiPolicy::Redact, PiiPolicy::Partial, PiiPolicy::Plain] {
+        let _policy = with_policy(policy);
+
+        let output = format!("{:?}", admin());
+
+        assert!(!output.contains(PASSWORD), "{policy:?}: {output}");
+        assert!(
+            output.contains("password: <hidden>"),
+            "{policy:?}: {output}"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode, header};
+use common::{Backend, TestApp, test_config};
+use play_security::config::{Secret, TokenFormat};
+use play_security::database::{Database, migrations};
+use sqlx::migrate::{MigrateError, Migrator};
+
+backend_tests!(
+    unknown_route_uses_error_route,
+    unknown_method_is_rejected,
+    malformed_json_is_rejected,
+    missing_content_type_is_rejected,
+    home_responds,
+    liveness_is_up,
+    readiness_checks_storage,
+    openapi_document_is_served,
+    request_id_is_echoed,
+);
+
+async fn unknown_route_uses_error_route(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.get("/does-not-exist", None).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.problem_code(), "not_found");
+    assert_eq!(response.json()["instance"], "/does-not-exist");
+}
+
+async fn unknown_method_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.request(Method::PATCH, "/login", None, None).await;
+
+    assert_eq!(response.status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+async fn malformed_json_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let request = Request::post("/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"username\": "))
+        .unwrap();
+    let response = app.send(request).await;
+
+    assert!(response.status.is_client_error(), "{}", response.status);
+    assert_eq!(response.problem_code(), "malformed_body");
+}
+
+async fn missing_content_type_is_rejected(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let request = Request::post("/login")
+        .body(Body::from("{\"username\": \"a\", \"password\": \"b\"}"))
+        .unwrap();
+    let response = app.send(request).await;
+
+    assert!(response.status.is_client_error(), "{}", response.status);
+    assert_eq!(response.problem_code(), "malformed_body");
+}
+
+async fn home_responds(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.get("/", None).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text(), "Hello Axum");
+}
+
+async fn liveness_is_up(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.get("/healthz", None).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+async fn readiness_checks_storage(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.get("/readyz", None).await;
+
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let checks = &response.json()["checks"];
+    assert_eq!(checks["database"]["status"], "up");
+    assert_eq!(checks["migrations"]["status"], "up");
+    assert_eq!(checks["signing_keys"]["status"], "up");
+}
+
+#[tokio::test]
+async fn readiness_hides_database_errors() {
+    let app = TestApp::new(Backend::Sqlite).await;
+    let Database::Sqlite(pool) = &app.database else {
+        unreachable!()
+    };
+    pool.close().await;
+
+    let response = app.get("/readyz", None).await;
+
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    let checks = &response.json()["checks"];
+    assert_eq!(checks["database"]["status"], "down");
+    assert_eq!(checks["database"]["detail"], "unreachable");
+}
+
+#[tokio::test]
+async fn readiness_fails_with_broken_signing_keys() {
+    let mut paseto = test_config(Backend::Memory);
+    paseto.auth.token_format = TokenFormat::PasetoV4Local;
+    paseto.auth.paseto_key = Secret::new("not-a-hex-key");
+    let mut jwt = test_config(Backend::Memory);
+    jwt.auth.jwt_secret = Secret::default();
+    let mut session_hash = test_config(Backend::Memory);
+    session_hash.auth.session_hash_key = Secret::default();
+
+    for config in [paseto, jwt, session_hash] {
+        let app = TestApp::with_config(config).await;
+
+        let response = app.get("/readyz", None).await;
+
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.json();
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["signing_keys"]["status"], "down");
+        assert_eq!(
+            body["checks"]["signing_keys"]["detail"],
+            "token round trip failed"
+        );
+        assert_eq!(body["checks"]["database"]["status"], "up");
+    }
+}
+
+async fn openapi_document_is_served(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let response = app.get("/openapi.json", None).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let doc = response.json();
+    assert!(doc["paths"]["/login"]["post"].is_object());
+    assert!(doc["paths"]["/users/{id}"]["get"].is_object());
+}
+
+async fn request_id_is_echoed(backend: Backend) {
+    let app = TestApp::new(backend).await;
+
+    let request = Request::get("/does-not-exist")
+        .header("X-Request-Id", "test-request-1")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+
+    assert_eq!(response.headers["x-request-id"], "test-request-1");
+    assert_eq!(response.json()["request_id"], "test-request-1");
+}
+
+#[tokio::test]
+async fn readiness_does_not_create_the_migrations_table() {
+    let app = TestApp::new(Backend::Sqlite).await;
+    let Database::Sqlite(pool) = &app.database else {
+        unreachable!()
+    };
+    sqlx::query("DROP TABLE _sqlx_migrations")
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let response = app.get("/readyz", None).await;
+
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json()["checks"]["migrations"]["status"], "down");
+    let tables: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE name = '_sqlx_migrations'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+    assert_eq!(tables, 0);
+}
+
+#[tokio::test]
+async fn database_migrated_by_another_app_is_refused() {
+    let app = TestApp::new(Backend::Sqlite).await;
+    let Database::Sqlite(pool) = &app.database else {
+        unreachable!()
+    };
+    // What first_axum's baseline, which reuses the version, leaves behind.
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = X'00' WHERE version = 20261018000000")
+        .execute(pool)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        app.database.migrate().await,
+        Err(MigrateError::VersionMismatch(20261018000000))
+    ));
+    let response = app.get("/readyz", None).await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json()["checks"]["migrations"]["status"], "down");
+}
+
+#[test]
+fn migration_sets_share_versions() {
+    let versions = |migrator: &Migrator| -> Vec<(i64, String)> {
+        migrator
+            .iter()
+            .filter(|m| m.migration_type.is_up_migration())
+            .map(|m| (m.version, m.description.to_string()))
+            .collect()
+    };
+
+    assert_eq!(
+        versions(&migrations::POSTGRES),
+        versions(&migrations::SQLITE)
+    );
+}
@@ -0,0 +1,262 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::{ADMIN, Backend, SESSION_HASH_KEY, TestApp, new_user, test_config};
+use play_security::auth::token::session_hash;
+use play_security::config::{Config, Secret, TokenFormat};
+use serde_json::json;
+
+backend_tests!(
+    opaque_token_grants_access,
+    opaque_logout_revokes_token,
+    opaque_password_change_revokes_token,
+    opaque_malformed_token_is_rejected,
+    opaque_unknown_token_is_rejected,
+    opaque_session_stores_hash,
+);
+
+const PASETO_KEY: &str = "707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f";
+const OTHER_PASETO_KEY: &str = "b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a3774";
+
+fn paseto_config(format: TokenFormat, key: &str) -> Config {
+    let mut config = test_config(Backend::Memory);
+    config.auth.token_format = format;
+    config.auth.paseto_key = Secret::new(key);
+    config
+}
+
+async fn paseto_app(format: TokenFormat) -> TestApp {
+    TestApp::with_config(paseto_config(format, PASETO_KEY)).await
+}
+
+#[tokio::test]
+async fn paseto_local_token_grants_access() {
+    let app = paseto_app(TokenFormat::PasetoV4Local).await;
+    let token = app.admin_token().await;
+
+    assert!(token.starts_with("v4.local."), "{token}");
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn paseto_public_token_grants_access() {
+    let app = paseto_app(TokenFormat::PasetoV4Public).await;
+    let token = app.admin_token().await;
+
+    assert!(token.starts_with("v4.public."), "{token}");
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn paseto_logout_revokes_token() {
+    let app = paseto_app(TokenFormat::PasetoV4Local).await;
+    let token = app.admin_token().await;
+
+    let response = app
+        .request(Method::POST, "/logout", Some(&token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    assert_eq!(
+        app.get("/users", Some(&token)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn paseto_tampered_token_is_rejected() {
+    for format in [TokenFormat::PasetoV4Local, TokenFormat::PasetoV4Public] {
+        let app = paseto_app(format).await;
+        let token = app.admin_token().await;
+
+        let mut tampered = token.into_bytes();
+        let middle = tampered.len() / 2;
+        tampered[middle] = if tampered[middle] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        let response = app.get("/users", Some(&tampered)).await;
+
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED, "{format:?}");
+        assert_eq!(response.problem_code(), "invalid_token");
+    }
+}
+
+#[tokio::test]
+async fn paseto_token_from_other_key_is_rejected() {
+    for format in [TokenFormat::PasetoV4Local, TokenFormat::PasetoV4Public] {
+        let app = paseto_app(format).await;
+        let other = TestApp::with_config(paseto_config(format, OTHER_PASETO_KEY)).await;
+        let token = other.admin_token().await;
+
+        let response = app.get("/users", Some(&token)).await;
+
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED, "{format:?}");
+        assert_eq!(response.json()["detail"], "InvalidSignature");
+    }
+}
+
+#[tokio::test]
+async fn paseto_previous_key_still_verifies() {
+    let mut config = paseto_config(TokenFormat::PasetoV4Local, OTHER_PASETO_KEY);
+    config.auth.previous_paseto_keys = vec![Secret::new(PASETO_KEY)];
+    let rotated = TestApp::with_config(config).await;
+    let app = paseto_app(TokenFormat::PasetoV4Local).await;
+    let token = app.admin_token().await;
+
+    // Decrypts with the previous key; the session lives in the other app.
+    let response = rotated.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "Session not found or expired");
+}
+
+#[tokio::test]
+async fn paseto_token_for_other_audience_is_rejected() {
+    let app = paseto_app(TokenFormat::PasetoV4Public).await;
+    let mut config = paseto_config(TokenFormat::PasetoV4Public, PASETO_KEY);
+    config.auth.audience = "another-service".to_string();
+    let other = TestApp::with_config(config).await;
+    let token = other.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "InvalidAudience");
+}
+
+#[tokio::test]
+async fn other_formats_are_rejected() {
+    let jwt = TestApp::new(Backend::Memory).await;
+    let local = paseto_app(TokenFormat::PasetoV4Local).await;
+    let public = paseto_app(TokenFormat::PasetoV4Public).await;
+    let jwt_token = jwt.admin_token().await;
+    let local_token = local.admin_token().await;
+    let public_token = public.admin_token().await;
+
+    for (app, token) in [
+        (&local, &jwt_token),
+        (&local, &public_token),
+        (&public, &local_token),
+        (&jwt, &local_token),
+    ] {
+        let response = app.get("/users", Some(token)).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response.problem_code(), "invalid_token");
+    }
+}
+
+#[tokio::test]
+async fn paseto_token_with_footer_is_rejected() {
+    for format in [TokenFormat::PasetoV4Local, TokenFormat::PasetoV4Public] {
+        let app = paseto_app(format).await;
+        let token = app.admin_token().await;
+        // base64url of `{"kid":"other"}`
+        let with_footer = format!("{token}.eyJraWQiOiJvdGhlciJ9");
+
+        let response = app.get("/users", Some(&with_footer)).await;
+
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED, "{format:?}");
+        assert_eq!(response.json()["detail"], "InvalidToken");
+    }
+}
+
+async fn opaque_app(backend: Backend) -> TestApp {
+    let mut config = test_config(backend);
+    config.auth.token_format = TokenFormat::Opaque;
+    TestApp::with_config(config).await
+}
+
+async fn opaque_token_grants_access(backend: Backend) {
+    let app = opaque_app(backend).await;
+    let token = app.admin_token().await;
+
+    assert_eq!(token.len(), 43, "{token}");
+    assert!(!token.contains('.'), "{token}");
+    // The second request is answered from the session cache.
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+}
+
+async fn opaque_logout_revokes_token(backend: Backend) {
+    let app = opaque_app(backend).await;
+    let token = app.admin_token().await;
+    app.get("/users", Some(&token)).await;
+
+    let response = app
+        .request(Method::POST, "/logout", Some(&token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app.get("/users", Some(&token)).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "Session not found or expired");
+}
+
+async fn opaque_password_change_revokes_token(backend: Backend) {
+    let app = opaque_app(backend).await;
+    let token = app.admin_token().await;
+    app.request(Method::POST, "/users", Some(&token), Some(new_user(1)))
+        .await;
+    let user_token = app.token("user1", "user1#01!").await;
+    let user = app.get("/users", Some(&user_token)).await.json();
+    let id = user["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|u| u["username"] == "user1")
+        .and_then(|u| u["id"].as_i64())
+        .unwrap();
+
+    app.request(
+        Method::PATCH,
+        &format!("/users/update_pwd/{id}"),
+        Some(&token),
+        Some(json!({ "password": "a-brand-new-password", "update_by": ADMIN })),
+    )
+    .await;
+
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+}
+
+async fn opaque_malformed_token_is_rejected(backend: Backend) {
+    let app = opaque_app(backend).await;
+    let jwt = TestApp::new(backend).await.admin_token().await;
+
+    for token in ["not-a-token", jwt.as_str()] {
+        let response = app.get("/users", Some(token)).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response.json()["detail"], "InvalidToken");
+    }
+}
+
+async fn opaque_unknown_token_is_rejected(backend: Backend) {
+    let app = opaque_app(backend).await;
+    let other = opaque_app(backend).await;
+    let token = other.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["detail"], "Session not found or expired");
+}
+
+async fn opaque_session_stores_hash(backend: Backend) {
+    let app = opaque_app(backend).await;
+    let token = app.admin_token().await;
+    let sessions = app.database.sessions();
+
+    assert_eq!(sessions.find_user(&token).await.unwrap(), None);
+    let hash = session_hash(&token, SESSION_HASH_KEY);
+    assert_eq!(
+        sessions
+            .find_user(&hash)
+            .await
+            .unwrap()
+            .map(|s| s.username)
+            .as_deref(),
+        Some(ADMIN)
+    );
+}
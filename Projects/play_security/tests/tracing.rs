@@ -0,0 +1,107 @@
+//! Exports spans through `tracing.file_path` and checks they continue the
+//! caller's `traceparent` whatever the log level. The subscriber is only
+//! installed on the test's thread, so each test runs on a current-thread
+//! runtime.
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{ADMIN, ADMIN_PASSWORD, Backend, TestApp, test_config};
+use play_security::telemetry::otel;
+use serde_json::{Value, json};
+use std::path::Path;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+/// The exported spans of one trace, as written by the file exporter.
+struct Trace(Vec<Value>);
+
+impl Trace {
+    fn read(path: &Path, trace_id: &str) -> Trace {
+        let raw = std::fs::read_to_string(path).unwrap();
+        Trace(
+            raw.lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap())
+                .filter(|span| span["trace_id"] == trace_id)
+                .collect(),
+        )
+    }
+
+    fn find(&self, predicate: impl Fn(&Value) -> bool) -> &Value {
+        self.0
+            .iter()
+            .find(|span| predicate(span))
+            .unwrap_or_else(|| panic!("span not exported, got {:#?}", self.0))
+    }
+
+    /// Whether following `parent_span_id` from `span` reaches `ancestor`.
+    fn descends_from(&self, span: &Value, ancestor: &Value) -> bool {
+        let mut parent = &span["parent_span_id"];
+        while let Some(current) = self.0.iter().find(|s| &s["span_id"] == parent) {
+            if current["span_id"] == ancestor["span_id"] {
+                return true;
+            }
+            parent = &current["parent_span_id"];
+        }
+        false
+    }
+}
+
+#[tokio::test]
+async fn spans_continue_the_callers_trace() {
+    let path = std::env::temp_dir().join(format!(
+        "play_security_spans_{:016x}.jsonl",
+        rand::random::<u64>()
+    ));
+    let mut config = test_config(Backend::Sqlite);
+    config.tracing.file_path = Some(path.clone());
+    let provider = otel::init(&config.tracing).unwrap().unwrap();
+    // As in `main`, with a log level that would hide every span if it
+    // applied to the export too.
+    let subscriber = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::sink)
+                .with_filter(EnvFilter::new("warn")),
+        )
+        .with(otel::layer(&provider));
+    let _subscriber = tracing::subscriber::set_default(subscriber);
+    let app = TestApp::with_config(config).await;
+
+    let request = Request::post("/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("traceparent", format!("00-{TRACE_ID}-{PARENT_SPAN_ID}-01"))
+        .body(Body::from(
+            json!({ "username": ADMIN, "password": ADMIN_PASSWORD }).to_string(),
+        ))
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+
+    let trace = Trace::read(&path, TRACE_ID);
+    let _ = std::fs::remove_file(&path);
+
+    let server = trace.find(|s| s["kind"] == "server");
+    assert_eq!(server["parent_span_id"], PARENT_SPAN_ID);
+    assert_eq!(
+        server["attributes"]["request_id"],
+        response.headers["x-request-id"].to_str().unwrap()
+    );
+
+    let query = trace.find(|s| {
+        s["kind"] == "client"
+            && s["attributes"]["db.statement"]
+                .as_str()
+                .is_some_and(|q| q.contains("FROM users"))
+    });
+    assert_eq!(query["attributes"]["db.system"], "sqlite");
+    assert!(trace.descends_from(query, server), "{query:#}");
+
+    let hash = trace.find(|s| s["name"] == "password.hash");
+    assert_eq!(hash["attributes"]["operation"], "verify");
+    assert!(trace.descends_from(hash, server), "{hash:#}");
+}
@@ -0,0 +1,405 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use chrono::Utc;
+use common::{ADMIN, Backend, TestApp, new_user};
+use play_security::auth::password::hash_password;
+use play_security::database::bootstrap::{BootstrapError, bootstrap_admin};
+use serde_json::json;
+
+backend_tests!(
+    list_returns_admin,
+    create_then_get,
+    create_rejects_duplicate,
+    create_validates_body,
+    get_unknown_user_is_not_found,
+    get_rejects_invalid_id,
+    update_changes_fields,
+    update_rename_ends_session,
+    update_rejects_taken_email,
+    update_unknown_user_is_not_found,
+    update_password_allows_login,
+    update_password_ends_session,
+    update_password_rejects_over_72_bytes,
+    remove_deletes_user,
+    remove_ends_session,
+    remove_unknown_user_is_not_found,
+    remove_multiple_deletes_listed_users,
+    remove_multiple_validates_ids,
+    production_refuses_admin_with_known_default,
+);
+
+/// Creates `new_user(n)` and returns its id.
+async fn create(app: &TestApp, token: &str, n: u32) -> i64 {
+    let response = app
+        .request(Method::POST, "/users", Some(token), Some(new_user(n)))
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    assert_eq!(response.json()["rows_affected"], 1);
+
+    let users = app.get("/users", Some(token)).await.json();
+    users["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|u| u["username"] == format!("user{n}"))
+        .and_then(|u| u["id"].as_i64())
+        .expect("created user is listed")
+}
+
+async fn list_returns_admin(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app.get("/users", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["data"][0]["username"], ADMIN);
+    assert!(body["data"][0].get("pwd").is_none());
+}
+
+async fn create_then_get(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let id = create(&app, &token, 1).await;
+    let response = app.get(&format!("/users/{id}"), Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let user = &response.json()["data"];
+    assert_eq!(user["username"], "user1");
+    assert_eq!(user["email"], "user1@example.com");
+    assert_eq!(user["created_by"], ADMIN);
+    assert!(user["write_date"].is_null());
+}
+
+async fn create_rejects_duplicate(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    create(&app, &token, 1).await;
+
+    let mut duplicate = new_user(2);
+    duplicate["email"] = json!("user1@example.com");
+    let response = app
+        .request(Method::POST, "/users", Some(&token), Some(duplicate))
+        .await;
+
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.problem_code(), "duplicate_value");
+    assert_eq!(response.json()["field"], "email");
+}
+
+async fn create_validates_body(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let mut invalid = new_user(1);
+    invalid["phone"] = json!("0821234567");
+    invalid["email"] = json!("not-an-email");
+    let response = app
+        .request(Method::POST, "/users", Some(&token), Some(invalid))
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.problem_code(), "validation_failed");
+    let fields: Vec<String> = response.json()["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap().to_string())
+        .collect();
+    assert!(fields.contains(&"phone".to_string()), "{fields:?}");
+    assert!(fields.contains(&"email".to_string()), "{fields:?}");
+}
+
+async fn get_unknown_user_is_not_found(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app.get("/users/999", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.problem_code(), "user_not_found");
+}
+
+async fn get_rejects_invalid_id(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app.get("/users/abc", Some(&token)).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.problem_code(), "invalid_path");
+}
+
+async fn update_changes_fields(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+
+    let mut changed = new_user(1);
+    changed["name"] = json!("Renamed");
+    changed["update_by"] = json!(ADMIN);
+    let response = app
+        .request(
+            Method::PUT,
+            &format!("/users/{id}"),
+            Some(&token),
+            Some(changed),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+
+    let user = app.get(&format!("/users/{id}"), Some(&token)).await.json();
+    assert_eq!(user["data"]["name"], "Renamed");
+    assert_eq!(user["data"]["update_by"], ADMIN);
+    assert!(!user["data"]["write_date"].is_null());
+}
+
+async fn update_rename_ends_session(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+    let user_token = app.token("user1", "user1#01!").await;
+    // Caches the session under the old username.
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::OK
+    );
+
+    let mut renamed = new_user(1);
+    renamed["username"] = json!("renamed1");
+    let response = app
+        .request(
+            Method::PUT,
+            &format!("/users/{id}"),
+            Some(&token),
+            Some(renamed),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+}
+
+async fn update_rejects_taken_email(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    create(&app, &token, 1).await;
+    let id = create(&app, &token, 2).await;
+
+    let mut changed = new_user(2);
+    changed["email"] = json!("user1@example.com");
+    let response = app
+        .request(
+            Method::PUT,
+            &format!("/users/{id}"),
+            Some(&token),
+            Some(changed),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.json()["field"], "email");
+}
+
+async fn update_unknown_user_is_not_found(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app
+        .request(Method::PUT, "/users/999", Some(&token), Some(new_user(1)))
+        .await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.problem_code(), "user_not_found");
+}
+
+async fn update_password_allows_login(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+
+    let response = app
+        .request(
+            Method::PATCH,
+            &format!("/users/update_pwd/{id}"),
+            Some(&token),
+            Some(json!({ "password": "a-brand-new-password", "update_by": ADMIN })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+
+    assert_eq!(
+        app.login("user1", "a-brand-new-password").await.status,
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.login("user1", "user1#01!").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+async fn update_password_ends_session(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+    let user_token = app.token("user1", "user1#01!").await;
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::OK
+    );
+
+    app.request(
+        Method::PATCH,
+        &format!("/users/update_pwd/{id}"),
+        Some(&token),
+        Some(json!({ "password": "a-brand-new-password", "update_by": ADMIN })),
+    )
+    .await;
+
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(app.get("/users", Some(&token)).await.status, StatusCode::OK);
+}
+
+async fn update_password_rejects_over_72_bytes(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+
+    // 40 characters, 80 bytes: bcrypt would drop everything past byte 72.
+    let response = app
+        .request(
+            Method::PATCH,
+            &format!("/users/update_pwd/{id}"),
+            Some(&token),
+            Some(json!({ "password": "é".repeat(40), "update_by": ADMIN })),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.problem_code(), "validation_failed");
+    assert_eq!(response.json()["errors"][0]["field"], "password");
+}
+
+async fn remove_deletes_user(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+
+    let response = app
+        .request(Method::DELETE, &format!("/users/{id}"), Some(&token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["rows_affected"], 1);
+
+    assert_eq!(
+        app.get(&format!("/users/{id}"), Some(&token)).await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+async fn remove_ends_session(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let id = create(&app, &token, 1).await;
+    let user_token = app.token("user1", "user1#01!").await;
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::OK
+    );
+
+    app.request(Method::DELETE, &format!("/users/{id}"), Some(&token), None)
+        .await;
+
+    assert_eq!(
+        app.get("/users", Some(&user_token)).await.status,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+async fn remove_unknown_user_is_not_found(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app
+        .request(Method::DELETE, "/users/999", Some(&token), None)
+        .await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.problem_code(), "user_not_found");
+}
+
+async fn remove_multiple_deletes_listed_users(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+    let first = create(&app, &token, 1).await;
+    let second = create(&app, &token, 2).await;
+    let kept = create(&app, &token, 3).await;
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/users/delete_multiple",
+            Some(&token),
+            Some(json!({ "ids": [first, second, 999] })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    assert_eq!(response.json()["rows_affected"], 2);
+
+    let users = app.get("/users", Some(&token)).await.json();
+    let ids: Vec<i64> = users["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| u["id"].as_i64().unwrap())
+        .collect();
+    assert!(ids.contains(&kept));
+    assert!(!ids.contains(&first) && !ids.contains(&second));
+}
+
+async fn remove_multiple_validates_ids(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let token = app.admin_token().await;
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/users/delete_multiple",
+            Some(&token),
+            Some(json!({ "ids": [] })),
+        )
+        .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.problem_code(), "validation_failed");
+}
+
+async fn production_refuses_admin_with_known_default(backend: Backend) {
+    let app = TestApp::new(backend).await;
+    let users = app.database.users();
+    let admin = users.list().await.unwrap().remove(0);
+    // Too short for the API, but an older database may still hold it.
+    let hash = hash_password("changeme").unwrap();
+    users
+        .update_password(admin.id, &hash, ADMIN, Utc::now().naive_utc())
+        .await
+        .unwrap();
+
+    let result = bootstrap_admin(app.database.users().as_ref(), None, true).await;
+
+    assert!(matches!(
+        result,
+        Err(BootstrapError::DefaultPassword(ref user)) if user == ADMIN
+    ));
+}